@@ -0,0 +1,102 @@
+use crate::{
+    save::Fields,
+    world::{Char, Pos, SUB},
+    H, W,
+};
+
+// The camera looks ahead of where the player is going, and leaves it alone
+// while it moves around the middle of the screen. All in cells.
+const LOOKAHEAD_X: isize = 10;
+const LOOKAHEAD_Y: isize = 4;
+const DEAD_X: isize = 6;
+const DEAD_Y: isize = 3;
+// Fraction of the way to its target the camera moves each tick.
+const EASE: isize = 8;
+
+/// Moves from `at` toward `target` once it is more than `dead` cells away.
+fn chase(at: isize, target: isize, dead: isize) -> isize {
+    let off = target - at;
+    let excess = off - off.clamp(-dead * SUB, dead * SUB);
+    at + excess / EASE
+}
+
+/// Keeps the center of a `view` cells wide window inside `size` cells.
+fn clamp(at: isize, size: isize, view: isize) -> isize {
+    let min = view * SUB / 2;
+    at.clamp(min, (size * SUB - min).max(min))
+}
+
+/// Which part of the level is on screen, for levels larger than it.
+#[derive(Clone)]
+pub struct Camera {
+    // Center of the view, in 1/SUB of a cell.
+    x: isize,
+    y: isize,
+    // Size of the level, in cells.
+    w: isize,
+    h: isize,
+}
+
+impl Camera {
+    /// A camera centered on `focus`, in a level of `w` by `h` cells.
+    pub fn new(w: isize, h: isize, focus: Pos) -> Self {
+        let mut camera = Self {
+            x: focus.x * SUB + SUB / 2,
+            y: focus.y * SUB + SUB / 2,
+            w,
+            h,
+        };
+        camera.clamp();
+        camera
+    }
+
+    /// Moves toward the character, one tick.
+    pub fn follow(&mut self, char: &Char) {
+        self.follow_x(char);
+        let target = char.fy + (char.vy * 8).clamp(-LOOKAHEAD_Y * SUB, LOOKAHEAD_Y * SUB);
+        self.y = chase(self.y, target, DEAD_Y);
+        self.y = clamp(self.y, self.h, H);
+    }
+
+    /// Rises by `speed`, or faster to keep the character out of the top
+    /// third of the screen, and follows it sideways. For levels that keep
+    /// growing upward, so there is no top edge to stop at.
+    pub fn climb(&mut self, speed: isize, char: &Char) {
+        self.follow_x(char);
+        self.y = (self.y - speed).min(char.fy + (H / 2 - H / 3) * SUB);
+    }
+
+    fn follow_x(&mut self, char: &Char) {
+        let target = char.fx + char.right_power * LOOKAHEAD_X * SUB;
+        self.x = chase(self.x, target, DEAD_X);
+        self.x = clamp(self.x, self.w, W);
+    }
+
+    /// As a line of the save file.
+    pub fn to_save(&self) -> String {
+        format!("{} {} {} {}", self.x, self.y, self.w, self.h)
+    }
+
+    pub fn from_save(mut f: Fields) -> Result<Self, String> {
+        Ok(Camera {
+            x: f.next()?,
+            y: f.next()?,
+            w: f.next()?,
+            h: f.next()?,
+        })
+    }
+
+    // Never shows past the edges of the level.
+    fn clamp(&mut self) {
+        self.x = clamp(self.x, self.w, W);
+        self.y = clamp(self.y, self.h, H);
+    }
+
+    /// The level cell shown in the top left corner of the screen.
+    pub fn origin(&self) -> Pos {
+        Pos {
+            x: self.x.div_euclid(SUB) - W / 2,
+            y: self.y.div_euclid(SUB) - H / 2,
+        }
+    }
+}
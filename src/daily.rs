@@ -0,0 +1,258 @@
+use std::{
+    fmt,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    error::Error,
+    physics::{PhysicsProfile, Preset},
+    replay,
+    settings::{Mode, Settings},
+    store,
+    world::{format_time, World},
+};
+
+const FILE: &str = "daily";
+const PREFIX: &str = "yjump-daily";
+
+/// A day in UTC, which picks the level of the daily challenge.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Date {
+    year: i64,
+    month: i64,
+    day: i64,
+}
+
+impl Date {
+    pub fn today() -> Self {
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        Date::from_days((secs / 86400) as i64)
+    }
+
+    /// The date `days` after 1970-01-01, in the proleptic Gregorian
+    /// calendar.
+    fn from_days(days: i64) -> Self {
+        let z = days + 719468;
+        let era = z.div_euclid(146097);
+        let doe = z - era * 146097;
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        Date {
+            year: yoe + era * 400 + (month <= 2) as i64,
+            month,
+            day,
+        }
+    }
+
+    /// Reads `YYYY-MM-DD`.
+    pub fn parse(s: &str) -> Option<Self> {
+        let mut parts = s.splitn(3, '-').map(|p| p.parse::<i64>().ok());
+        let date = Date {
+            year: parts.next()??,
+            month: parts.next()??,
+            day: parts.next()??,
+        };
+        ((1..=12).contains(&date.month) && (1..=31).contains(&date.day)).then_some(date)
+    }
+
+    /// The seed of the day's level, the same for everyone.
+    pub fn seed(self) -> usize {
+        // xorshift never leaves zero
+        replay::hash(format!("{} {}", PREFIX, self).as_bytes()) as usize | 1
+    }
+
+    /// Where the replay of the day's official attempt is kept.
+    pub fn replay_file(self) -> String {
+        format!("daily-{}", self)
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+/// The rules of the daily challenge, the same for everyone whatever their
+/// settings.
+pub fn rules() -> (Settings, PhysicsProfile) {
+    let settings = Settings {
+        mode: Mode::Daily,
+        ..Settings::default()
+    };
+    (settings, PhysicsProfile::preset(Preset::Classic))
+}
+
+/// A daily result, as pasted in chat:
+/// `yjump-daily/<date>/<score>/<time>/<replay hash>`.
+pub struct Share {
+    pub date: Date,
+    pub score: u64,
+    pub time: String,
+    pub hash: u64,
+}
+
+impl Share {
+    pub fn parse(s: &str) -> Option<Self> {
+        let mut parts = s.trim().split('/');
+        if parts.next()? != PREFIX {
+            return None;
+        }
+        let share = Share {
+            date: Date::parse(parts.next()?)?,
+            score: parts.next()?.parse().ok()?,
+            time: parts.next()?.into(),
+            hash: u64::from_str_radix(parts.next()?, 16).ok()?,
+        };
+        parts.next().is_none().then_some(share)
+    }
+}
+
+impl fmt::Display for Share {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}/{}/{}/{}/{:016x}",
+            PREFIX, self.date, self.score, self.time, self.hash
+        )
+    }
+}
+
+/// The day of the last official attempt, and its result once it has one.
+fn load() -> Result<(Option<Date>, Option<Share>), Error> {
+    let Some(text) = store::read(FILE)? else {
+        return Ok((None, None));
+    };
+    let (mut attempt, mut share) = (None, None);
+    store::parse(FILE, &text, |key, value| match key {
+        "attempt" => {
+            attempt = Some(Date::parse(value).ok_or("expected a date")?);
+            Ok(())
+        }
+        "result" => {
+            share = Some(Share::parse(value).ok_or("not a daily result")?);
+            Ok(())
+        }
+        _ => Err(format!("unknown daily value `{}`", key)),
+    })?;
+    Ok((attempt.or(share.as_ref().map(|s| s.date)), share))
+}
+
+/// Starts a daily run. The first one started on the day of its level is the
+/// official attempt, and it is recorded right away: restarting it or leaving
+/// it does not give another try.
+pub fn begin(world: &mut World) -> Result<(), Error> {
+    let today = Date::today();
+    let (attempt, _) = load()?;
+    if world.seed == today.seed() && attempt != Some(today) {
+        store::write(FILE, &format!("attempt = {}\n", today))?;
+        world.official = true;
+    }
+    Ok(())
+}
+
+/// Wraps up a daily run, finished or left before its end. The official
+/// attempt keeps its replay and saves its result to share, other runs are
+/// practice. Returns whether it was official, and lines telling the player.
+pub fn finish(world: &World) -> Result<(bool, Vec<String>), Error> {
+    let (attempt, share) = load()?;
+    match (attempt, world.replay()) {
+        (Some(date), Some(replay)) if world.official => {
+            let file = date.replay_file();
+            replay.save(&file)?;
+            let share = Share {
+                date,
+                score: world.result(),
+                time: format_time(world.frames()),
+                hash: replay.hash(),
+            };
+            store::write(FILE, &format!("attempt = {}\nresult = {}\n", date, share))?;
+            let mut notes = vec!["Official result, to share:".into(), share.to_string()];
+            if let Some(path) = store::path(&file) {
+                notes.push("Replay:".into());
+                notes.push(path.display().to_string());
+            }
+            Ok((true, notes))
+        }
+        _ => match share.filter(|s| s.date == Date::today()) {
+            Some(share) => Ok((
+                false,
+                vec!["Practice run, today's result:".into(), share.to_string()],
+            )),
+            None => Ok((false, vec!["Practice run".into()])),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use super::*;
+    use crate::world::tests::play;
+
+    #[test]
+    fn days_count_from_the_epoch() {
+        let date = |days| Date::from_days(days).to_string();
+        assert_eq!(date(0), "1970-01-01");
+        assert_eq!(date(19782), "2024-02-29");
+        assert_eq!(date(11017), "2000-03-01");
+        assert_eq!(date(-1), "1969-12-31");
+    }
+
+    #[test]
+    fn dates_parse_as_written() {
+        let parse = |s| Date::parse(s).map(|d| d.to_string());
+        assert_eq!(parse("2026-10-18").as_deref(), Some("2026-10-18"));
+        assert_eq!(parse("2026-1-8").as_deref(), Some("2026-01-08"));
+        assert!(parse("2026-13-01").is_none());
+        assert!(parse("2026-10-00").is_none());
+        assert!(parse("2026-10").is_none());
+        assert!(parse("today").is_none());
+    }
+
+    #[test]
+    fn shared_results_read_back() {
+        let text = "yjump-daily/2026-10-18/12/60.00/3f2a9c1b7e4d2a10";
+        let share = Share::parse(text).expect("a daily result");
+        assert_eq!(share.score, 12);
+        assert_eq!(share.time, "60.00");
+        assert_eq!(share.to_string(), text);
+        assert!(Share::parse("yjump-weekly/2026-10-18/12/60.00/3f2a9c1b7e4d2a10").is_none());
+        assert!(Share::parse(&format!("{}/more", text)).is_none());
+    }
+
+    #[test]
+    fn a_second_run_that_day_is_practice() {
+        let dir = env::temp_dir().join(format!("yjump-daily-{}", process::id()));
+        // The only test that touches stored files, so nothing else minds
+        // where they go.
+        env::set_var("XDG_CONFIG_HOME", &dir);
+        let (settings, physics) = rules();
+        let day = || World::new(Date::today().seed(), &settings, &physics);
+
+        let mut first = day();
+        begin(&mut first).unwrap_or_else(|e| panic!("{}", e));
+        assert!(first.official);
+        let mut second = day();
+        begin(&mut second).unwrap_or_else(|e| panic!("{}", e));
+        assert!(!second.official);
+
+        for _ in 0..600 {
+            play(&mut first);
+        }
+        let (counts, notes) = finish(&first).unwrap_or_else(|e| panic!("{}", e));
+        assert!(counts);
+        let (counts, practice) = finish(&second).unwrap_or_else(|e| panic!("{}", e));
+        assert!(!counts);
+        assert_eq!(practice[1], notes[1]);
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,231 @@
+use crossterm::style::Color;
+
+use crate::{
+    events::GameEvent,
+    physics::PhysicsProfile,
+    settings::Mode,
+    world::{press, Action, Cell, Char, Dash, Map, Pos, Rand},
+};
+
+// Ticks between two teleports, and how late the mimic copies the player.
+const TELEPORT_COOLDOWN: usize = 120;
+pub const MIMIC_DELAY: isize = 30;
+
+/// What a character is, and for enemies how they behave.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Player,
+    /// Jumps around at random when the player comes close.
+    Jumper,
+    /// Dashes away from the player.
+    Runner,
+    /// Like the jumper, but cannot double jump.
+    Heavy,
+    /// Vanishes to somewhere else when the player comes close.
+    Teleporter,
+    /// Replays the player's own inputs, a little late.
+    Mimic,
+    /// Chases the player, and ends a survival run when it catches them.
+    Hunter,
+}
+
+impl Kind {
+    const ALL: [Kind; 7] = [
+        Kind::Player,
+        Kind::Jumper,
+        Kind::Runner,
+        Kind::Heavy,
+        Kind::Teleporter,
+        Kind::Mimic,
+        Kind::Hunter,
+    ];
+
+    /// Name in save files.
+    pub fn key(self) -> &'static str {
+        match self {
+            Kind::Player => "player",
+            Kind::Jumper => "jumper",
+            Kind::Runner => "runner",
+            Kind::Heavy => "heavy",
+            Kind::Teleporter => "teleporter",
+            Kind::Mimic => "mimic",
+            Kind::Hunter => "hunter",
+        }
+    }
+
+    pub fn parse(key: &str) -> Option<Self> {
+        Kind::ALL.into_iter().find(|k| k.key() == key)
+    }
+
+    /// Picks what the enemy number `index` of a wave is. Each wave lets
+    /// in a harder kind, and past the fifth the easiest ones stop coming.
+    /// Hunters only show up in survival, one more every third wave up to
+    /// three.
+    pub fn pick(mode: Mode, wave: usize, index: usize, rand: &mut Rand) -> Kind {
+        if mode == Mode::Survival && index <= (wave.saturating_sub(1) / 3).min(2) {
+            return Kind::Hunter;
+        }
+        let kinds = [
+            Kind::Jumper,
+            Kind::Heavy,
+            Kind::Mimic,
+            Kind::Runner,
+            Kind::Teleporter,
+        ];
+        let hardest = (wave + 1).min(kinds.len());
+        let easiest = wave.saturating_sub(4).min(2);
+        kinds[easiest + rand.next() % (hardest - easiest)]
+    }
+
+    pub fn color(self) -> Color {
+        match self {
+            Kind::Player => Color::Yellow,
+            Kind::Jumper => Color::Green,
+            Kind::Runner => Color::Cyan,
+            Kind::Heavy => Color::Grey,
+            Kind::Teleporter => Color::Magenta,
+            Kind::Mimic => Color::Blue,
+            Kind::Hunter => Color::Red,
+        }
+    }
+
+    /// Color of the trail left behind while moving.
+    pub fn trail_color(self) -> Color {
+        match self {
+            Kind::Player => Color::DarkYellow,
+            Kind::Jumper => Color::DarkGreen,
+            Kind::Runner => Color::DarkCyan,
+            Kind::Heavy => Color::DarkGrey,
+            Kind::Teleporter => Color::DarkMagenta,
+            Kind::Mimic => Color::DarkBlue,
+            Kind::Hunter => Color::DarkRed,
+        }
+    }
+
+    /// The player and jumpers show which way they face, the others have
+    /// their own letter.
+    pub fn glyph(self, right_power: isize) -> char {
+        match (self, right_power) {
+            (Kind::Player | Kind::Jumper, 1) => '>',
+            (Kind::Player | Kind::Jumper, -1) => '<',
+            (Kind::Player | Kind::Jumper, _) => 'Y',
+            (Kind::Runner, _) => 'R',
+            (Kind::Heavy, _) => 'H',
+            (Kind::Teleporter, _) => 'T',
+            (Kind::Mimic, _) => 'M',
+            (Kind::Hunter, _) => 'X',
+        }
+    }
+
+    /// Score for tagging one.
+    pub fn points(self) -> usize {
+        match self {
+            Kind::Player => 0,
+            Kind::Jumper | Kind::Heavy => 1,
+            Kind::Mimic => 2,
+            Kind::Runner | Kind::Teleporter => 3,
+            Kind::Hunter => 5,
+        }
+    }
+
+    pub fn double_jumps(self) -> bool {
+        self != Kind::Heavy
+    }
+}
+
+/// What an enemy knows about the world when deciding what to do.
+pub struct Senses<'a> {
+    pub player: &'a Char,
+    pub map: &'a Map,
+    pub physics: &'a PhysicsProfile,
+    /// Squared distance under which enemies notice the player.
+    pub reaction: isize,
+    pub frames: isize,
+    /// The player's inputs from `MIMIC_DELAY` ticks ago.
+    pub echo: &'a [Action],
+}
+
+/// Decides what an enemy does this tick, before it moves.
+pub fn think(ennemy: &mut Char, senses: &Senses, rand: &mut Rand, events: &mut Vec<GameEvent>) {
+    let player = senses.player;
+    let dist = (player.pos.x - ennemy.pos.x).pow(2) + (player.pos.y - ennemy.pos.y).pow(2);
+    let near = dist < senses.reaction;
+    ennemy.cooldown = ennemy.cooldown.saturating_sub(1);
+    match ennemy.kind {
+        Kind::Player => {}
+        Kind::Jumper | Kind::Heavy => {
+            if near {
+                ennemy.jump = 3;
+                ennemy.right_power = -1 + (rand.next() % 3) as isize;
+                if ennemy.right_power == 0
+                    || (ennemy.pos.x == 1 && ennemy.right_power == -1)
+                    || (ennemy.pos.x == senses.map.w - 2 && ennemy.right_power == 1)
+                {
+                    ennemy.right_power = -1 + (rand.next() % 3) as isize;
+                }
+            } else {
+                ennemy.jump = 0
+            }
+        }
+        Kind::Runner => {
+            if near {
+                let away = (ennemy.pos.x - player.pos.x).signum();
+                ennemy.right_power = if away == 0 {
+                    [-1, 1][rand.next() % 2]
+                } else {
+                    away
+                };
+                if ennemy.dash == Dash::Ready {
+                    ennemy.dash = Dash::Dashing(senses.physics.dash_length);
+                }
+                ennemy.jump = 3;
+            } else {
+                ennemy.jump = 0
+            }
+        }
+        Kind::Teleporter => {
+            if near && ennemy.cooldown == 0 {
+                if let Some(pos) = free_cell(senses.map, rand) {
+                    events.push(GameEvent::Teleport {
+                        from: ennemy.pos,
+                        to: pos,
+                    });
+                    ennemy.place(pos);
+                    ennemy.vx = 0;
+                    ennemy.vy = 0;
+                    ennemy.fly = true;
+                    ennemy.cooldown = TELEPORT_COOLDOWN;
+                }
+            }
+        }
+        Kind::Mimic => {
+            for action in senses.echo {
+                press(ennemy, *action, senses.frames, senses.physics);
+            }
+        }
+        Kind::Hunter => {
+            let dx = (player.pos.x - ennemy.pos.x).signum();
+            if dx != 0 {
+                ennemy.right_power = dx;
+            }
+            // Hops toward the player, and uses the double jump on the way
+            // down when the player is above.
+            let above = player.pos.y < ennemy.pos.y - 1;
+            ennemy.jump = if !ennemy.fly || (above && ennemy.vy > 0) {
+                3
+            } else {
+                0
+            };
+        }
+    }
+}
+
+/// A random empty cell of the level, if one turns up after a few tries.
+fn free_cell(map: &Map, rand: &mut Rand) -> Option<Pos> {
+    (0..10)
+        .map(|_| Pos {
+            x: 1 + (rand.next() % (map.w - 2) as usize) as isize,
+            y: map.top + 1 + (rand.next() % (map.h - 2).max(1) as usize) as isize,
+        })
+        .find(|p| matches!(map.get(p.x, p.y), Cell::Air))
+}
@@ -0,0 +1,49 @@
+use std::{fmt, io};
+
+pub enum Error {
+    NotATerminal,
+    Terminal(io::Error),
+    Io(io::Error),
+    Config(String),
+    /// The command line makes no sense.
+    Usage,
+    /// A result or replay checked from the command line does not hold up.
+    Verify(String),
+}
+
+impl Error {
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Error::Io(_) => 1,
+            Error::Usage => 2,
+            Error::NotATerminal | Error::Terminal(_) => 3,
+            Error::Config(_) => 4,
+            Error::Verify(_) => 5,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::NotATerminal => write!(
+                f,
+                "stdout is not a terminal, run yjump directly in a terminal without redirecting its output"
+            ),
+            Error::Terminal(e) => write!(f, "could not set up the terminal: {}", e),
+            Error::Io(e) => write!(f, "i/o error: {}", e),
+            Error::Config(msg) => write!(f, "config: {}", msg),
+            Error::Usage => write!(
+                f,
+                "usage: yjump [watch <replay file> | verify [<daily result>] <replay file>]"
+            ),
+            Error::Verify(msg) => write!(f, "not verified: {}", msg),
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
@@ -0,0 +1,50 @@
+use crate::{enemy::Kind, world::Pos};
+
+/// Something that happened in the world during a tick. The simulation only
+/// reports these, scoring, effects, sound and stats each pick what they
+/// care about.
+#[derive(Clone, Copy)]
+pub enum GameEvent {
+    /// Left the ground, jumping or falling off a ledge.
+    TakeOff {
+        kind: Kind,
+        pos: Pos,
+    },
+    /// Jumped off the ground or a wall.
+    Jump {
+        kind: Kind,
+    },
+    DoubleJump {
+        kind: Kind,
+    },
+    Dash {
+        kind: Kind,
+    },
+    Land {
+        kind: Kind,
+        pos: Pos,
+    },
+    /// Someone was stomped or knocked back by a dash.
+    Hit {
+        pos: Pos,
+    },
+    /// A teleporter vanished and showed up somewhere else.
+    Teleport {
+        from: Pos,
+        to: Pos,
+    },
+    /// The player tagged an enemy, which is out of the wave.
+    Tag(Tag),
+    /// The last enemy of a wave was tagged.
+    WaveClear,
+}
+
+/// How the player tagged an enemy, for bonuses.
+#[derive(Clone, Copy)]
+pub struct Tag {
+    pub kind: Kind,
+    pub pos: Pos,
+    pub air: bool,
+    pub double_jump: bool,
+    pub dash: bool,
+}
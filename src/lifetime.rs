@@ -0,0 +1,190 @@
+use crate::{
+    enemy::Kind,
+    error::Error,
+    events::GameEvent,
+    store::{self, Choice},
+    world::{World, MAX_MULTIPLIER},
+    FPS,
+};
+
+const FILE: &str = "stats";
+// Tags in a row without landing for `Achievement::SkyHigh`.
+const SKY_HIGH_TAGS: usize = 3;
+const DASH_TAGS: usize = 10;
+const MANY_TAGS: usize = 100;
+const HIGH_WAVE: usize = 10;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Achievement {
+    FirstTag,
+    SkyHigh,
+    DashTags,
+    FullCombo,
+    HighWave,
+    Hunter,
+    ManyTags,
+    Hour,
+}
+
+impl Choice for Achievement {
+    const ALL: &'static [Self] = &[
+        Achievement::FirstTag,
+        Achievement::SkyHigh,
+        Achievement::DashTags,
+        Achievement::FullCombo,
+        Achievement::HighWave,
+        Achievement::Hunter,
+        Achievement::ManyTags,
+        Achievement::Hour,
+    ];
+
+    fn key(self) -> &'static str {
+        match self {
+            Achievement::FirstTag => "first_tag",
+            Achievement::SkyHigh => "sky_high",
+            Achievement::DashTags => "dash_tags",
+            Achievement::FullCombo => "full_combo",
+            Achievement::HighWave => "high_wave",
+            Achievement::Hunter => "hunter",
+            Achievement::ManyTags => "many_tags",
+            Achievement::Hour => "hour",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Achievement::FirstTag => "First tag",
+            Achievement::SkyHigh => "Sky high",
+            Achievement::DashTags => "Dasher",
+            Achievement::FullCombo => "Full combo",
+            Achievement::HighWave => "Wave rider",
+            Achievement::Hunter => "Hunter hunter",
+            Achievement::ManyTags => "Centurion",
+            Achievement::Hour => "Regular",
+        }
+    }
+}
+
+impl Achievement {
+    pub fn description(self) -> String {
+        match self {
+            Achievement::FirstTag => "Tag an enemy".into(),
+            Achievement::SkyHigh => {
+                format!("Tag {} enemies without touching the ground", SKY_HIGH_TAGS)
+            }
+            Achievement::DashTags => format!("Tag {} enemies with a dash", DASH_TAGS),
+            Achievement::FullCombo => format!("Chain a x{} combo", MAX_MULTIPLIER),
+            Achievement::HighWave => format!("Reach wave {}", HIGH_WAVE),
+            Achievement::Hunter => "Tag the hunter in survival".into(),
+            Achievement::ManyTags => format!("Tag {} enemies in total", MANY_TAGS),
+            Achievement::Hour => "Play for an hour in total".into(),
+        }
+    }
+}
+
+/// Totals over every run played, and the achievements unlocked.
+#[derive(Default)]
+pub struct Lifetime {
+    pub tags: usize,
+    pub jumps: usize,
+    pub double_jumps: usize,
+    pub dashes: usize,
+    pub dash_tags: usize,
+    /// Ticks spent playing.
+    pub frames: isize,
+    pub longest_combo: usize,
+    pub highest_wave: usize,
+    /// In the order they were unlocked.
+    pub unlocked: Vec<Achievement>,
+    /// Tags since the player last stood on something, not kept.
+    air_tags: usize,
+}
+
+impl Lifetime {
+    pub fn load() -> Result<Self, Error> {
+        let mut stats = Lifetime::default();
+        let Some(text) = store::read(FILE)? else {
+            return Ok(stats);
+        };
+        store::parse(FILE, &text, |key, value| {
+            let number = || value.parse().map_err(|_| "expected a number".to_string());
+            match key {
+                "tags" => stats.tags = number()?,
+                "jumps" => stats.jumps = number()?,
+                "double_jumps" => stats.double_jumps = number()?,
+                "dashes" => stats.dashes = number()?,
+                "dash_tags" => stats.dash_tags = number()?,
+                "frames" => stats.frames = number()? as isize,
+                "longest_combo" => stats.longest_combo = number()?,
+                "highest_wave" => stats.highest_wave = number()?,
+                "achievement" => stats.unlocked.push(Achievement::parse(value)?),
+                _ => return Err(format!("unknown stat `{}`", key)),
+            }
+            Ok(())
+        })?;
+        Ok(stats)
+    }
+
+    pub fn save(&self) -> Result<(), Error> {
+        let mut text = format!(
+            "tags = {}\njumps = {}\ndouble_jumps = {}\ndashes = {}\ndash_tags = {}\nframes = {}\nlongest_combo = {}\nhighest_wave = {}\n",
+            self.tags,
+            self.jumps,
+            self.double_jumps,
+            self.dashes,
+            self.dash_tags,
+            self.frames,
+            self.longest_combo,
+            self.highest_wave
+        );
+        for achievement in &self.unlocked {
+            text += &format!("achievement = {}\n", achievement.key());
+        }
+        store::write(FILE, &text)
+    }
+
+    /// Counts what happened in the tick the world just played, and returns
+    /// the achievements it unlocked.
+    pub fn record(&mut self, world: &World) -> Vec<Achievement> {
+        self.frames += 1;
+        let mut hunted = false;
+        for event in world.events() {
+            match *event {
+                GameEvent::Jump { kind: Kind::Player } => self.jumps += 1,
+                GameEvent::DoubleJump { kind: Kind::Player } => self.double_jumps += 1,
+                GameEvent::Dash { kind: Kind::Player } => self.dashes += 1,
+                GameEvent::Land {
+                    kind: Kind::Player, ..
+                } => self.air_tags = 0,
+                GameEvent::Tag(tag) => {
+                    self.tags += 1;
+                    self.dash_tags += tag.dash as usize;
+                    self.air_tags = if tag.air { self.air_tags + 1 } else { 0 };
+                    hunted |= tag.kind == Kind::Hunter;
+                }
+                _ => {}
+            }
+        }
+        self.longest_combo = self.longest_combo.max(world.combo());
+        self.highest_wave = self.highest_wave.max(world.wave());
+
+        let mut new = Vec::new();
+        for &achievement in Achievement::ALL {
+            let earned = match achievement {
+                Achievement::FirstTag => self.tags >= 1,
+                Achievement::SkyHigh => self.air_tags >= SKY_HIGH_TAGS,
+                Achievement::DashTags => self.dash_tags >= DASH_TAGS,
+                Achievement::FullCombo => self.longest_combo >= MAX_MULTIPLIER,
+                Achievement::HighWave => self.highest_wave >= HIGH_WAVE,
+                Achievement::Hunter => hunted,
+                Achievement::ManyTags => self.tags >= MANY_TAGS,
+                Achievement::Hour => self.frames >= 3600 * FPS as isize,
+            };
+            if earned && !self.unlocked.contains(&achievement) {
+                self.unlocked.push(achievement);
+                new.push(achievement);
+            }
+        }
+        new
+    }
+}
@@ -1,733 +1,781 @@
 use std::{
-    io::{stdout, Stdout, Write},
-    time::Duration,
+    io::{stdout, IsTerminal, Stdout},
+    path::Path,
+    process::ExitCode,
+    time::{Duration, Instant},
 };
 
-use crossterm::{
-    cursor,
-    event::{self, *},
-    execute, queue, style, terminal,
-};
+use crossterm::{event::*, style, terminal};
+
+mod camera;
+mod daily;
+mod enemy;
+mod error;
+mod events;
+mod lifetime;
+mod menu;
+mod motion;
+mod particles;
+mod physics;
+mod render;
+mod replay;
+mod save;
+mod scores;
+mod settings;
+mod sound;
+mod sprites;
+mod store;
+#[cfg(feature = "synth")]
+mod synth;
+mod term;
+mod timing;
+mod verify;
+mod viewer;
+mod world;
+
+use error::Error;
+use lifetime::Lifetime;
+use menu::{Menu, Nav, OverItem, PauseItem, SettingItem, TitleItem};
+use particles::{draw_particles, emit, update_particles, Effect};
+use physics::{PhysicsProfile, Preset, Watcher};
+use render::Screen;
+use replay::{Playback, Replay};
+use scores::{Entry, HighScores};
+use settings::{Controls, Mode, Settings};
+use sound::Sound;
+use sprites::Sprites;
+use store::Choice;
+use timing::{Clock, Stats};
+use viewer::Viewer;
+use world::{Action, Pos, Rand, World};
 
 const W: isize = 80;
 const H: isize = 24;
 const FPS: i64 = 60;
-#[derive(Clone, Copy)]
-struct Pos {
-    x: isize,
-    y: isize,
-}
-#[repr(u8)]
-#[derive(Clone, Copy)]
-enum Cell {
-    Air,
-    Solid,
-    Wall,
-}
-#[derive(Clone, Copy)]
-struct Pixel {
-    back: style::Color,
-    front: style::Color,
-    char: char,
-}
-
-type Map = [Cell; (W * H) as usize];
-fn gen_map(rand: &mut Rand) -> Map {
-    let mut map = [Cell::Air; (W * H) as usize];
-    for x in 0..W {
-        for y in 0..H {
-            let index = (x + y * W) as usize;
-            let border = x == 0 || x == W - 1 || y == 0 || y == H - 1;
-            if border {
-                map[index] = Cell::Wall;
-            } else {
-                let r = rand.next() % 60;
 
-                if r < 1 {
-                    map[index] = Cell::Solid;
-                    let len = 2 + (rand.next() % 2) as isize;
-                    for u in -len..len {
-                        if x + u > 0 && x + u < W - 1 {
-                            let index = (x + u + y * W) as usize;
-                            map[index] = Cell::Solid;
-                        }
-                    }
-                }
-            }
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("yjump: {}", e);
+            ExitCode::from(e.exit_code())
         }
     }
-    map
 }
 
-struct Rand(usize);
-impl Rand {
-    pub fn next(&mut self) -> usize {
-        self.0 ^= self.0 << 13;
-        self.0 ^= self.0 >> 17;
-        self.0 ^= self.0 << 5;
-        self.0
+// The guard is dropped before `main` reports an error, so the message ends
+// up on the normal screen.
+fn run() -> Result<(), Error> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let watch = match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        [] => None,
+        ["watch", path] => {
+            let replay = Replay::read(Path::new(path))?;
+            replay
+                .playable()
+                .map_err(|e| Error::Config(format!("{}: {}", path, e)))?;
+            Some(replay)
+        }
+        ["verify", replay] => {
+            println!("{}", verify::replay(Path::new(replay))?);
+            return Ok(());
+        }
+        ["verify", result, replay] => {
+            println!("{}", verify::daily(result, Path::new(replay))?);
+            return Ok(());
+        }
+        _ => return Err(Error::Usage),
+    };
+    let mut stdout = stdout();
+    if !stdout.is_terminal() {
+        return Err(Error::NotATerminal);
     }
+    let settings = Settings::load()?;
+    let physics = settings.physics.load()?;
+    let scores = HighScores::load()?;
+    let lifetime = Lifetime::load()?;
+    let signals = term::Signals::register().map_err(Error::Terminal)?;
+    let _guard = term::Guard::new().map_err(Error::Terminal)?;
+
+    game(
+        &mut stdout,
+        &signals,
+        settings,
+        physics,
+        scores,
+        lifetime,
+        watch,
+    )
 }
-#[derive(Clone, Copy, PartialEq, Eq)]
-enum Dash {
-    Ready,
-    Dashing(usize),
-    Loading(usize),
-}
-struct Char {
-    pos: Pos,
-    old_pos: Pos,
-    right_power: isize,
-    last_power_frame: isize,
-    jump: i32,
-    double_jump_ready: bool,
-    fly: bool,
-    down: bool,
-    dy: isize,
-    dx: isize,
-    player: bool,
-    dash: Dash,
-    phase: isize,
+
+enum State {
+    Title(Menu<TitleItem>),
+    /// The high scores, or the lifetime stats on the next page.
+    Scores {
+        stats: bool,
+    },
+    Playing,
+    Paused {
+        menu: Menu<PauseItem>,
+        settings: Option<Menu<SettingItem>>,
+        // Paused by the terminal losing focus, so regaining it resumes.
+        by_focus: bool,
+    },
+    Over(Menu<OverItem>),
+    Watching(Box<Viewer>),
 }
 
-impl Default for Char {
-    fn default() -> Self {
-        Self {
-            pos: Pos { x: W / 2, y: H - 2 },
-            old_pos: Pos { x: W / 2, y: H - 2 },
-            right_power: 0_isize,
-            last_power_frame: 0,
-            jump: 0,
-            double_jump_ready: true,
-            fly: false,
-            down: false,
-            dy: 0_isize,
-            dx: 0,
-            player: true,
-            dash: Dash::Ready,
-            phase: 0,
+impl State {
+    fn paused(by_focus: bool) -> Self {
+        State::Paused {
+            menu: menu::pause_menu(),
+            settings: None,
+            by_focus,
         }
     }
 }
 
-fn update_char(
-    char: &mut Char,
-    frames: isize,
-    map: &mut Map,
-    rand: &mut Rand,
-    particles: &mut Vec<Particle>,
-) {
-    char.dash = match char.dash {
-        Dash::Loading(x) if x > 0 => Dash::Loading(x - 1),
-        Dash::Dashing(x) if x > 0 => Dash::Dashing(x - 1),
-        Dash::Dashing(_) => Dash::Loading(60),
-        _ => Dash::Ready,
-    };
-    char.old_pos = char.pos;
-    let dashing = matches!(char.dash, Dash::Dashing(_));
-
-    let color = if char.player {
-        style::Color::Yellow
-    } else {
-        style::Color::Green
-    };
-
-    if dashing {
-        char.pos.x += char.right_power;
-        char.pos.x = char.pos.x.max(1).min(W - 2);
-        char.dx = char.right_power;
-        char.dy = 0;
-        return;
+fn action(code: KeyCode, controls: Controls) -> Option<Action> {
+    let wasd = matches!(controls, Controls::All | Controls::Wasd);
+    let zqsd = matches!(controls, Controls::All | Controls::Zqsd);
+    match code {
+        KeyCode::Right => Some(Action::Right),
+        KeyCode::Left => Some(Action::Left),
+        KeyCode::Up => Some(Action::Jump),
+        KeyCode::Down => Some(Action::Down),
+        KeyCode::Char('d') if wasd || zqsd => Some(Action::Right),
+        KeyCode::Char('s') if wasd || zqsd => Some(Action::Down),
+        KeyCode::Char('a') if wasd => Some(Action::Left),
+        KeyCode::Char('w') if wasd => Some(Action::Jump),
+        KeyCode::Char('q') if zqsd => Some(Action::Left),
+        KeyCode::Char('z') if zqsd => Some(Action::Jump),
+        _ => None,
     }
+}
 
-    let fly0 = char.fly;
-    if !char.fly && char.jump > 0 {
-        char.double_jump_ready = true;
-        char.dy -= 5 + if char.right_power == 0 { 1 } else { 0 };
-        char.dx = char.right_power;
-        char.fly = true;
-        char.jump = 0;
-    }
+/// A line of text shown at the bottom of the screen for a few seconds.
+struct Notice {
+    text: String,
+    until: isize,
+}
 
-    if !char.fly {
-        let cell = map[(char.pos.x + (char.pos.y + 1) * W) as usize];
-        match cell {
-            Cell::Solid | Cell::Wall => {}
-            _ => {
-                char.fly = true;
-            }
+impl Notice {
+    fn draw(&self, screen: &mut Screen, palette: &render::Palette, frames: isize) {
+        if frames < self.until {
+            let x = W - 1 - self.text.chars().count() as isize;
+            screen.print(x, H - 1, &self.text, palette.hud, style::Color::White);
         }
     }
+}
 
-    if char.fly && !fly0 {
-        char.phase = frames;
-        spawn_particles(particles, char.pos, 5, rand, color);
-    }
-
-    let alt3: bool = (frames - char.phase + 1) % 3 == 0;
-
-    if char.fly {
-        if char.jump > 0 && char.double_jump_ready {
-            char.double_jump_ready = false;
-            char.dy = 0;
-            char.dy -= 5 + if char.right_power == 0 { 1 } else { 0 };
-            char.dx = char.right_power;
-        }
-
-        if char.down {
-            char.dx = 0;
-            char.dy = char.dy.max(0);
-        }
-
-        if alt3 {
-            char.pos.x += char.dx;
+/// Shows a failure on screen instead of ending the session. Used for the
+/// files written while playing, as losing a setting or a score is not worth
+/// losing the run over.
+fn report<T>(notice: &mut Notice, result: Result<T, Error>, frames: isize) -> Option<T> {
+    match result {
+        Ok(value) => Some(value),
+        Err(e) => {
+            *notice = Notice {
+                text: format!(" {} ", e),
+                until: frames + 5 * FPS as isize,
+            };
+            None
         }
+    }
+}
 
-        char.pos.x = char.pos.x.max(1).min(W - 2);
-
-        let mut floored = false;
-
-        for _ in 0..if char.dy.abs() >= 5 || alt3 { 1 } else { 0 } {
-            let mut ceiled = false;
-            if char.dy.signum() > 0 {
-                let cell = map[(char.pos.x + (char.pos.y + 1) * W) as usize];
-                match cell {
-                    Cell::Solid | Cell::Wall => {
-                        floored = true;
-                    }
-                    _ => {}
-                }
+/// Switches to the physics chosen in the settings, in the running world
+/// too. A broken physics file is only reported on screen, as it is most
+/// likely being edited.
+fn apply_physics(
+    settings: &Settings,
+    physics: &mut PhysicsProfile,
+    world: &mut World,
+    notice: &mut Notice,
+    frames: isize,
+) {
+    match settings.physics.load() {
+        Ok(p) => {
+            *physics = p;
+            // The daily challenge is played with its own physics.
+            if world.mode != Mode::Daily {
+                world.set_physics(physics);
             }
-            if char.dy.signum() < 0 {
-                let cell = map[(char.pos.x + (char.pos.y - 1) * W) as usize];
-                match cell {
-                    Cell::Wall => ceiled = true,
-                    _ => {}
+            if settings.physics == Preset::Custom {
+                *notice = Notice {
+                    text: " physics loaded ".into(),
+                    until: frames + 2 * FPS as isize,
                 }
             }
-
-            if ceiled {
-                char.dy = 0
-            }
-            if floored {
-                char.dy = 0;
-            }
-            if !ceiled && !floored {
-                char.pos.y += char.dy.signum();
+        }
+        Err(e) => {
+            *notice = Notice {
+                text: format!(" {} ", e),
+                until: frames + 5 * FPS as isize,
             }
         }
+    }
+}
 
-        if floored {
-            char.fly = false;
-            spawn_particles(particles, char.pos, 5, rand, color);
-            char.dy = 0;
-            char.dx = 0
-        }
-        if char.fly && alt3 {
-            char.dy += 1;
+/// Records a finished run in the high scores, and keeps its replay when it
+/// is the best of its mode. Returns whether it is.
+fn submit(world: &World, scores: &mut HighScores) -> Result<bool, Error> {
+    let best = scores.submit(Entry {
+        mode: world.mode,
+        value: world.result(),
+        seed: world.seed,
+    })?;
+    if best {
+        if let Some(replay) = world.replay() {
+            replay.save(&best_file(world.mode))?;
         }
     }
-    char.down = false;
-    char.jump = (char.jump - 1).max(0);
+    Ok(best)
 }
 
-struct Particle {
-    p: Pos,
-    dx: isize,
-    dy: isize,
-    life: isize,
-    kind: char,
-    color: style::Color,
+/// Wide frames cover a cell beside the character, which is a matter of
+/// taste, so they are a setting. They also need that cell to be on screen,
+/// so they only show while the terminal shows the whole board.
+fn wide_sprites(settings: &Settings, terminal: (u16, u16)) -> bool {
+    settings.wide_sprites && term::shows_board(terminal)
 }
 
-fn spawn_particles(
-    particles: &mut Vec<Particle>,
-    pos: Pos,
-    count: usize,
-    rand: &mut Rand,
-    color: style::Color,
-) {
-    for _ in 0..count {
-        let p = Particle {
-            p: pos,
-            dx: -3 + (rand.next() % 7) as isize,
-            dy: -3 + (rand.next() % 5) as isize,
-            life: 10 + (rand.next() % 10) as isize,
-            kind: ['*', '.', '¨', '¤', '\'', '²', '·'][rand.next() % 7],
-            color,
-        };
-        particles.push(p);
+/// Classic runs have no end, they count once they are left for good. So
+/// does an official daily attempt left before its end, as it is not played
+/// again.
+fn retire(world: &World, scores: &mut HighScores) -> Result<(), Error> {
+    if world.mode == Mode::Classic && world.score() > 0 {
+        submit(world, scores)?;
+    } else if world.official && !world.finished() {
+        daily::finish(world)?;
+        submit(world, scores)?;
     }
+    Ok(())
 }
-fn main() -> std::io::Result<()> {
-    let mut stdout = stdout();
-    execute!(
-        stdout,
-        terminal::EnterAlternateScreen,
-        event::EnableFocusChange,
-        cursor::DisableBlinking,
-        cursor::Hide
-    )?;
-    terminal::enable_raw_mode()?;
 
-    let _ = game(&mut stdout);
+/// The fastest run of a time attack with these settings and physics, to
+/// race against. One that can't be read is only reported, the run goes on
+/// without it.
+fn best_run(
+    settings: &Settings,
+    physics: &PhysicsProfile,
+    notice: &mut Notice,
+    frames: isize,
+) -> Option<Replay> {
+    if !settings.mode.lower_is_better() {
+        return None;
+    }
+    report(notice, Replay::load(&best_file(settings.mode)), frames)
+        .flatten()
+        .filter(|r| r.fits(settings, physics))
+}
 
-    execute!(
-        stdout,
-        cursor::Show,
-        style::ResetColor,
-        event::DisableFocusChange,
-        terminal::LeaveAlternateScreen
-    )?;
-    terminal::disable_raw_mode()?;
-    Ok(())
+fn best_file(mode: Mode) -> String {
+    format!("best-{}", mode.key())
 }
 
-fn game(stdout: &mut Stdout) -> std::io::Result<()> {
-    let rand = &mut Rand(5);
-    let mut player = Char::default();
-    let mut enemies = Vec::new();
-    for _ in 0..2 {
-        let r = (rand.next() % 30) as isize;
-        let mut char = Char::default();
-        char.pos = Pos {
-            x: if rand.next() % 2 == 0 { r } else { W - 1 - r },
-            y: H - 2,
-        };
-        char.old_pos = char.pos;
-        char.player = false;
-        enemies.push(char);
+/// The ghost of a continued run, caught up with it.
+fn catch_up(world: &World, notice: &mut Notice, frames: isize) -> Option<Playback> {
+    let replay = world.replay()?;
+    let mut ghost = best_run(&replay.settings(), &replay.physics, notice, frames)
+        .filter(|r| r.seed == world.seed)
+        .map(|r| Playback::new(&r));
+    if let Some(ghost) = &mut ghost {
+        for _ in 0..world.frames() {
+            ghost.step();
+        }
     }
-    let mut particles: Vec<Particle> = Vec::new();
-
-    let mut menu = true;
-    let mut paused = false;
+    ghost
+}
 
-    let mut pixels = [Pixel {
-        back: style::Color::Black,
-        front: style::Color::Black,
-        char: ' ',
-    }; (W * H) as usize];
+/// Starts a run, with the ghost of the best one when it was on this seed.
+/// Daily runs are always on the level of the day, under its rules.
+fn start(
+    seed: usize,
+    settings: &Settings,
+    physics: &PhysicsProfile,
+    notice: &mut Notice,
+    frames: isize,
+) -> (World, Option<Playback>) {
+    if settings.mode == Mode::Daily {
+        let (settings, physics) = daily::rules();
+        let mut world = World::new(daily::Date::today().seed(), &settings, &physics);
+        report(notice, daily::begin(&mut world), frames);
+        return (world, None);
+    }
+    let ghost = best_run(settings, physics, notice, frames)
+        .filter(|r| r.seed == seed)
+        .map(|r| Playback::new(&r));
+    (World::new(seed, settings, physics), ghost)
+}
 
-    let mut pixels_drawn = [Pixel {
-        back: style::Color::Black,
-        front: style::Color::Black,
-        char: '£',
-    }; (W * H) as usize];
+fn game(
+    stdout: &mut Stdout,
+    signals: &term::Signals,
+    mut settings: Settings,
+    mut physics: PhysicsProfile,
+    mut scores: HighScores,
+    mut lifetime: Lifetime,
+    watch: Option<Replay>,
+) -> Result<(), Error> {
+    let mut world = World::new(5, &settings, &physics);
+    let mut ghost: Option<Playback> = None;
+    let mut saved = save::exists();
+    let mut state = match watch {
+        Some(replay) => State::Watching(Box::new(Viewer::new(replay))),
+        None => State::Title(menu::title_menu(saved)),
+    };
+    let mut screen = Screen::new();
 
-    let mut map = gen_map(rand);
+    // Title screen ambience, kept out of the world so it never touches its
+    // random sequence.
+    let rand = &mut Rand::from_time();
+    let mut particles = Vec::new();
     let mut frames = 0;
-    let mut score = 0;
-    let mut switching = 0;
 
-    loop {
-        let start = std::time::Instant::now();
-        frames += 1;
+    let mut watcher = Watcher::new();
+    let mut notice = Notice {
+        text: String::new(),
+        until: 0,
+    };
+
+    let mut clock = Clock::new();
+    let mut stats = Stats::new();
+    let mut audio =
+        report(&mut notice, sound::output(settings.sound), frames).unwrap_or_else(sound::silent);
+    let mut sprites = Sprites::load(wide_sprites(
+        &settings,
+        terminal::size().unwrap_or_default(),
+    ))?;
+
+    'game: loop {
+        match signals.take() {
+            Some(term::Signal::Quit) => break 'game,
+            Some(term::Signal::Suspend) => {
+                term::suspend()?;
+                screen.invalidate();
+                clock.reset();
+            }
+            Some(term::Signal::Resume) => {
+                term::enter()?;
+                screen.invalidate();
+                clock.reset();
+            }
+            None => {}
+        }
         while poll(Duration::from_millis(0))? {
             let e = read()?;
             match e {
-                Event::FocusGained if paused => {
-                    std::thread::sleep(Duration::from_millis(200));
-                    pixels_drawn = [Pixel {
-                        back: style::Color::Black,
-                        front: style::Color::Black,
-                        char: '£',
-                    }; (W * H) as usize];
-
-                    paused = false
+                Event::Key(KeyEvent {
+                    code: KeyCode::Char('c'),
+                    modifiers: KeyModifiers::CONTROL,
+                    ..
+                }) => break 'game,
+                Event::Key(KeyEvent {
+                    code: KeyCode::Char('z'),
+                    modifiers: KeyModifiers::CONTROL,
+                    ..
+                }) => {
+                    term::suspend()?;
+                    screen.invalidate();
+                    clock.reset();
                 }
-                Event::FocusLost if !paused => {
-                    queue!(
-                        stdout,
-                        cursor::MoveTo((W / 2 - 3) as u16, (H / 2 - 1) as u16)
-                    )?;
-                    queue!(stdout, style::SetBackgroundColor(style::Color::Red))?;
-                    queue!(stdout, style::SetForegroundColor(style::Color::Black))?;
-                    queue!(stdout, style::SetAttribute(style::Attribute::Bold))?;
-                    queue!(stdout, style::Print("PAUSED"))?;
-                    queue!(
-                        stdout,
-                        style::SetAttribute(style::Attribute::NormalIntensity)
-                    )?;
-                    queue!(stdout, cursor::MoveTo((W - 1) as u16, (H - 1) as u16))?;
-                    stdout.flush()?;
-                    paused = true
+                Event::Key(KeyEvent {
+                    code: KeyCode::F(3),
+                    ..
+                }) => {
+                    settings.show_fps = !settings.show_fps;
+                    report(&mut notice, settings.save(), frames);
                 }
-                Event::Key(event) if menu => match event {
-                    KeyEvent {
-                        code: KeyCode::Char('c'),
-                        modifiers: KeyModifiers::CONTROL,
-                        kind: KeyEventKind::Press,
-                        state: KeyEventState::NONE,
-                    }
-                    | KeyEvent {
-                        code: KeyCode::Esc, ..
-                    } => {
-                        return Ok(());
+                Event::FocusGained => {
+                    if let State::Paused { by_focus: true, .. } = state {
+                        state = State::Playing
                     }
-                    _ => menu = false,
-                },
-                Event::Key(event) if !menu => match event {
-                    KeyEvent {
-                        code: KeyCode::Char('c'),
-                        modifiers: KeyModifiers::CONTROL,
-                        kind: KeyEventKind::Press,
-                        state: KeyEventState::NONE,
-                    }
-                    | KeyEvent {
-                        code: KeyCode::Esc, ..
-                    } => {
-                        return Ok(());
+                }
+                Event::FocusLost if settings.pause_on_focus_loss => {
+                    if let State::Playing = state {
+                        state = State::paused(true)
                     }
-
-                    KeyEvent {
-                        code: KeyCode::Char('d') | KeyCode::Right,
-                        ..
-                    } => {
-                        if player.dash == Dash::Ready
-                            && player.right_power == 1
-                            && (frames - player.last_power_frame) < 20
-                        {
-                            player.dash = Dash::Dashing(20)
+                }
+                Event::Key(KeyEvent { code, .. }) => match &mut state {
+                    State::Title(_) if code == KeyCode::Esc => break 'game,
+                    State::Title(menu) => match menu::nav(code) {
+                        Some(Nav::Up) => menu.up(),
+                        Some(Nav::Down) => menu.down(),
+                        Some(nav @ (Nav::Left | Nav::Right | Nav::Select)) => {
+                            let delta = if let Nav::Left = nav { -1 } else { 1 };
+                            match menu.current() {
+                                TitleItem::Continue => {
+                                    if let Nav::Select = nav {
+                                        saved = false;
+                                        *menu = menu::title_menu(saved);
+                                        match save::take() {
+                                            Ok(Some(w)) => {
+                                                report(
+                                                    &mut notice,
+                                                    retire(&world, &mut scores),
+                                                    frames,
+                                                );
+                                                world = w;
+                                                ghost = catch_up(&world, &mut notice, frames);
+                                                state = State::Playing
+                                            }
+                                            Ok(None) => {}
+                                            Err(e) => {
+                                                notice = Notice {
+                                                    text: format!(" {} ", e),
+                                                    until: frames + 5 * FPS as isize,
+                                                }
+                                            }
+                                        }
+                                    }
+                                }
+                                TitleItem::Play => {
+                                    if let Nav::Select = nav {
+                                        if world.finished() || !world.matches(&settings) {
+                                            report(
+                                                &mut notice,
+                                                retire(&world, &mut scores),
+                                                frames,
+                                            );
+                                            (world, ghost) = start(
+                                                rand.next(),
+                                                &settings,
+                                                &physics,
+                                                &mut notice,
+                                                frames,
+                                            );
+                                        }
+                                        state = State::Playing
+                                    }
+                                }
+                                TitleItem::Setting(item) => {
+                                    menu::change_setting(&mut settings, item, delta);
+                                    report(&mut notice, settings.save(), frames);
+                                    if let SettingItem::Physics = item {
+                                        apply_physics(
+                                            &settings,
+                                            &mut physics,
+                                            &mut world,
+                                            &mut notice,
+                                            frames,
+                                        );
+                                    }
+                                }
+                                TitleItem::Scores => {
+                                    if let Nav::Select = nav {
+                                        state = State::Scores { stats: false }
+                                    }
+                                }
+                                TitleItem::Watch => {
+                                    if let Nav::Select = nav {
+                                        let best = Replay::load(&best_file(settings.mode));
+                                        match report(&mut notice, best, frames) {
+                                            Some(Some(replay)) => {
+                                                state =
+                                                    State::Watching(Box::new(Viewer::new(replay)))
+                                            }
+                                            Some(None) => {
+                                                notice = Notice {
+                                                    text: " no best run to watch yet ".into(),
+                                                    until: frames + 2 * FPS as isize,
+                                                }
+                                            }
+                                            None => {}
+                                        }
+                                    }
+                                }
+                                TitleItem::Quit => {
+                                    if let Nav::Select = nav {
+                                        break 'game;
+                                    }
+                                }
+                            }
                         }
-                        if let Dash::Dashing(_) = player.dash {
-                        } else {
-                            player.right_power = 1;
-                            player.last_power_frame = frames;
+                        _ => {}
+                    },
+                    State::Scores { stats } => match menu::nav(code) {
+                        Some(Nav::Select | Nav::Back) => {
+                            state = State::Title(menu::title_menu(saved))
                         }
+                        Some(Nav::Left) => *stats = false,
+                        Some(Nav::Right) => *stats = true,
+                        _ => {}
+                    },
+                    State::Watching(_) if matches!(code, KeyCode::Esc | KeyCode::Char('q')) => {
+                        state = State::Title(menu::title_menu(saved))
                     }
-                    KeyEvent {
-                        code: KeyCode::Char('q') | KeyCode::Char('a') | KeyCode::Left,
-                        ..
-                    } => {
-                        if player.dash == Dash::Ready
-                            && player.right_power == -1
-                            && (frames - player.last_power_frame) < 20
-                        {
-                            player.dash = Dash::Dashing(20)
-                        }
-                        if let Dash::Dashing(_) = player.dash {
-                        } else {
-                            player.right_power = -1;
-                            player.last_power_frame = frames;
+                    State::Watching(viewer) => match code {
+                        KeyCode::Char(' ') | KeyCode::Char('p') => viewer.toggle(),
+                        KeyCode::Right | KeyCode::Char('.') => viewer.step(1),
+                        KeyCode::Left | KeyCode::Char(',') => viewer.step(-1),
+                        KeyCode::Up | KeyCode::Char('+') => viewer.faster(1),
+                        KeyCode::Down | KeyCode::Char('-') => viewer.faster(-1),
+                        KeyCode::Char('n') | KeyCode::Tab => viewer.next_tag(),
+                        _ => {}
+                    },
+                    State::Playing => match code {
+                        KeyCode::Esc => break 'game,
+                        KeyCode::Char('m') => state = State::Title(menu::title_menu(saved)),
+                        KeyCode::Char('p') => state = State::paused(false),
+                        code => {
+                            if let Some(action) = action(code, settings.controls) {
+                                world.press(action)
+                            }
                         }
-                    }
-                    KeyEvent {
-                        code: KeyCode::Char('z') | KeyCode::Char('w') | KeyCode::Up,
-                        ..
-                    } => {
-                        player.jump = 10;
-                    }
-                    KeyEvent {
-                        code: KeyCode::Char('s') | KeyCode::Down,
-                        ..
-                    } => {
-                        player.down = true;
-                        player.right_power = 0;
-                    }
-                    KeyEvent {
-                        code: KeyCode::Char('m'),
+                    },
+                    State::Paused {
+                        settings: Some(menu),
                         ..
-                    } => menu = true,
-                    _ => {}
+                    } => match menu::nav(code) {
+                        Some(Nav::Up) => menu.up(),
+                        Some(Nav::Down) => menu.down(),
+                        Some(nav @ (Nav::Left | Nav::Right | Nav::Select)) => {
+                            match menu.current() {
+                                SettingItem::Back => state = State::paused(false),
+                                item => {
+                                    let delta = if let Nav::Left = nav { -1 } else { 1 };
+                                    menu::change_setting(&mut settings, item, delta);
+                                    report(&mut notice, settings.save(), frames);
+                                    match item {
+                                        SettingItem::Physics => apply_physics(
+                                            &settings,
+                                            &mut physics,
+                                            &mut world,
+                                            &mut notice,
+                                            frames,
+                                        ),
+                                        SettingItem::Sound => {
+                                            report(&mut notice, audio.finish(), frames);
+                                            audio = report(
+                                                &mut notice,
+                                                sound::output(settings.sound),
+                                                frames,
+                                            )
+                                            .unwrap_or_else(sound::silent);
+                                        }
+                                        SettingItem::WideSprites => {
+                                            sprites.wide = wide_sprites(
+                                                &settings,
+                                                terminal::size().unwrap_or_default(),
+                                            )
+                                        }
+                                        _ => {}
+                                    }
+                                }
+                            }
+                        }
+                        Some(Nav::Back) => state = State::paused(false),
+                        None => {}
+                    },
+                    State::Paused {
+                        menu, settings: s, ..
+                    } => match menu::nav(code) {
+                        Some(Nav::Up) => menu.up(),
+                        Some(Nav::Down) => menu.down(),
+                        Some(Nav::Back) => state = State::Playing,
+                        Some(Nav::Select) => match menu.current() {
+                            PauseItem::Resume => state = State::Playing,
+                            PauseItem::Restart => {
+                                report(&mut notice, retire(&world, &mut scores), frames);
+                                (world, ghost) =
+                                    start(world.seed, &settings, &physics, &mut notice, frames);
+                                state = State::Playing
+                            }
+                            PauseItem::NewSeed => {
+                                report(&mut notice, retire(&world, &mut scores), frames);
+                                (world, ghost) =
+                                    start(rand.next(), &settings, &physics, &mut notice, frames);
+                                state = State::Playing
+                            }
+                            PauseItem::Settings => *s = Some(menu::settings_menu()),
+                            PauseItem::Title => state = State::Title(menu::title_menu(saved)),
+                        },
+                        _ => {}
+                    },
+                    State::Over(menu) => match menu::nav(code) {
+                        Some(Nav::Up) => menu.up(),
+                        Some(Nav::Down) => menu.down(),
+                        Some(Nav::Select) => match menu.current() {
+                            OverItem::Retry => {
+                                (world, ghost) =
+                                    start(world.seed, &settings, &physics, &mut notice, frames);
+                                state = State::Playing
+                            }
+                            OverItem::Race => {
+                                if let Some(best) =
+                                    best_run(&settings, &physics, &mut notice, frames)
+                                {
+                                    (world, ghost) =
+                                        start(best.seed, &settings, &physics, &mut notice, frames);
+                                    state = State::Playing
+                                }
+                            }
+                            OverItem::NewSeed => {
+                                (world, ghost) =
+                                    start(rand.next(), &settings, &physics, &mut notice, frames);
+                                state = State::Playing
+                            }
+                            OverItem::Watch => {
+                                if let Some(replay) = world.replay() {
+                                    state = State::Watching(Box::new(Viewer::new(replay)))
+                                }
+                            }
+                            OverItem::Title => state = State::Title(menu::title_menu(saved)),
+                        },
+                        Some(Nav::Back) => state = State::Title(menu::title_menu(saved)),
+                        _ => {}
+                    },
                 },
-                Event::Resize(_, _) => {
-                    pixels_drawn = [Pixel {
-                        back: style::Color::Black,
-                        front: style::Color::Black,
-                        char: '£',
-                    }; (W * H) as usize];
+                Event::Resize(cols, rows) => {
+                    screen.invalidate();
+                    sprites.wide = wide_sprites(&settings, (cols, rows));
                 }
                 _ => {}
             }
         }
-        if paused {
-            std::thread::sleep(Duration::from_millis(16));
-            continue;
+
+        if settings.physics == Preset::Custom && frames % FPS as isize == 0 && watcher.changed() {
+            apply_physics(&settings, &mut physics, &mut world, &mut notice, frames);
         }
 
-        if menu {
-            for y in 0..H {
-                for x in 0..W {
-                    let index = (x + y * W) as usize;
-                    let border = x == 0 || x == W - 1 || y == 0 || y == H - 1;
-                    let mut front = style::Color::Black;
-                    let mut back = style::Color::Black;
-                    let mut char = ' ';
-                    if border {
-                        back = style::Color::DarkBlue;
-                        front = style::Color::DarkBlue;
-                        char = ' ';
+        let ticks = clock.ticks();
+        let tick_start = Instant::now();
+        for _ in 0..ticks {
+            frames += 1;
+            match &mut state {
+                State::Title(_) | State::Scores { .. } => {
+                    let color = if rand.next() % 2 == 0 {
+                        style::Color::Yellow
+                    } else {
+                        style::Color::Green
+                    };
+                    if frames % 60 == (rand.next() % 60) as isize {
+                        emit(
+                            &mut particles,
+                            Effect::Ambience,
+                            Pos {
+                                x: (rand.next() % W as usize) as isize,
+                                y: (rand.next() % H as usize) as isize,
+                            },
+                            rand,
+                            color,
+                        )
                     }
-
-                    pixels[index] = Pixel { back, front, char };
-                }
-            }
-            let x = &mut 2_isize;
-            let y = &mut 2_isize;
-            let mut pprint = |x: &mut isize, y: &mut isize, c, back, front| {
-                if c == '\n' {
-                    *x = 2;
-                    *y += 1;
-                    return;
-                }
-                let index = (*x + *y * W) as usize;
-                pixels[index] = Pixel {
-                    back,
-                    front,
-                    char: c,
-                };
-                *x += 1;
-                if *x >= W - 1 {
-                    *x = 2;
-                    *y += 1;
+                    update_particles(&mut particles);
                 }
-            };
-            let text = r#"
-
-Stupid terminal game.
-Arrow keys (or WASD / ZSQD) to play.
-
-- Choose a direction, then jump with up arrow. Only key press counts.
-- Stop a jump with down arrow.
-- Double jump with up arrow.
-- Dash by double-tapping left or right.
-
-The goal is to collide with the other characters.
-"#;
-            for c in "yjump ".chars().chain(env!("CARGO_PKG_VERSION").chars()) {
-                pprint(x, y, c, style::Color::Black, style::Color::Green);
-            }
-            for c in text.chars() {
-                pprint(x, y, c, style::Color::Black, style::Color::White);
-            }
-            *y += 4;
-            *x = 25;
-            for c in "  PRESS ANY KEY TO START  ".chars() {
-                let color = if (frames + *x) % 100 < 50 {
-                    style::Color::DarkBlue
-                } else {
-                    style::Color::DarkGreen
-                };
-                pprint(x, y, c, color, style::Color::White);
-            }
-            *y = H - 2;
-            *x = 45;
-            for c in "Thomas SIMON <mail@thomassimon.dev".chars() {
-                pprint(x, y, c, style::Color::Black, style::Color::Green);
-            }
-
-            let color = if rand.next() % 2 == 0 {
-                style::Color::Yellow
-            } else {
-                style::Color::Green
-            };
-            if frames % 60 == (rand.next() % 60) as isize {
-                spawn_particles(
-                    &mut particles,
-                    Pos {
-                        x: (rand.next() % W as usize) as isize,
-                        y: (rand.next() % H as usize) as isize,
-                    },
-                    5,
-                    rand,
-                    color,
-                )
-            }
-        } else {
-            update_char(&mut player, frames, &mut map, rand, &mut particles);
-            switching = (switching - 1).max(0);
-
-            for ennemy in enemies.iter_mut() {
-                let dist =
-                    (player.pos.x - ennemy.pos.x).pow(2) + (player.pos.y - ennemy.pos.y).pow(2);
-
-                if dist < 70 {
-                    ennemy.jump = 3;
-                    ennemy.right_power = -1 + (rand.next() % 3) as isize;
-                    if ennemy.right_power == 0
-                        || (ennemy.pos.x == 1 && ennemy.right_power == -1)
-                        || (ennemy.pos.x == W - 2 && ennemy.right_power == 1)
-                    {
-                        ennemy.right_power = -1 + (rand.next() % 3) as isize;
+                State::Playing => {
+                    world.step();
+                    for sound in world.events().iter().filter_map(Sound::of) {
+                        audio.play(sound);
                     }
-                } else {
-                    ennemy.jump = 0
-                }
-
-                if switching == 0 && player.pos.x == ennemy.pos.x && player.pos.y == ennemy.pos.y {
-                    switching = 60;
-                    map = gen_map(rand);
-                    score += 1;
-                }
-
-                update_char(ennemy, frames, &mut map, rand, &mut particles);
-            }
-
-            for y in 0..H {
-                for x in 0..W {
-                    let index = (x + y * W) as usize;
-                    let cell = &map[index];
-                    let mut color = match cell {
-                        Cell::Wall => style::Color::DarkBlue,
-                        Cell::Air => style::Color::Black,
-                        Cell::Solid => style::Color::DarkBlue,
-                    };
-                    if switching > 0 {
-                        color = style::Color::DarkBlue;
-                        if (y > 4 && y < 17 && x > 45 && x < 53)
-                            || (y > 4 && y < 8 && x > 41 && x < 53)
-                            || (y > 5 && y < 16 && x > 30 && x < 35)
-                            || (y > 9 && y < 12 && x > 24 && x < 41)
-                        {
-                            color = style::Color::Green;
-                        }
+                    // A sound file that can no longer be written is given up
+                    // on, the run goes on without sound.
+                    if report(&mut notice, audio.tick(), frames).is_none() {
+                        audio = sound::silent();
                     }
-                    pixels[index] = Pixel {
-                        back: color,
-                        front: color,
-                        char: ' ',
-                    };
-                }
-            }
-
-            {
-                let mut x = 1;
-                for c in "yjump ".chars().chain(env!("CARGO_PKG_VERSION").chars()) {
-                    pixels[x] = Pixel {
-                        back: style::Color::DarkBlue,
-                        front: style::Color::White,
-                        char: c,
-                    };
-                    x += 1;
-                }
-            }
-            {
-                let alt = switching > 0 && switching % 8 < 4;
-                let sep = if alt { '-' } else { ' ' };
-
-                let s = format!("{}Score: {}{}", sep, score, sep);
-                let mut x = W as usize / 2 - s.len() / 2;
-                for c in s.chars() {
-                    pixels[x] = Pixel {
-                        back: style::Color::DarkBlue,
-                        front: style::Color::White,
-                        char: c,
-                    };
-                    x += 1;
-                }
-            }
-
-            for c in enemies.iter().chain(std::iter::once(&player)) {
-                let logo = match c.right_power {
-                    1 => '>',
-                    -1 => '<',
-                    _ => 'Y',
-                };
-                {
-                    let mut sx = c.old_pos.x;
-                    let mut sy = c.old_pos.y;
-                    while sx != c.pos.x || sy != c.pos.y {
-                        let index = (sx + sy * W) as usize;
-                        pixels[index] = Pixel {
-                            back: if c.player {
-                                style::Color::DarkYellow
-                            } else {
-                                style::Color::DarkGreen
-                            },
-                            front: style::Color::Black,
-                            char: logo,
+                    let unlocked = lifetime.record(&world);
+                    if let Some(last) = unlocked.last() {
+                        notice = Notice {
+                            text: format!(" Achievement unlocked: {} ", last.label()),
+                            until: frames + 5 * FPS as isize,
                         };
-                        let dy = (c.pos.y - sy).signum();
-                        sy += dy;
-                        let dx = (c.pos.x - sx).signum();
-                        sx += dx;
+                        report(&mut notice, lifetime.save(), frames);
                     }
-                }
-                {
-                    let index = (c.pos.x + c.pos.y * W) as usize;
-                    pixels[index] = Pixel {
-                        back: if c.player {
-                            match c.dash {
-                                Dash::Dashing(_) => style::Color::White,
-                                Dash::Loading(_) => style::Color::DarkYellow,
-                                Dash::Ready => style::Color::Yellow,
-                            }
+                    if let Some(ghost) = &mut ghost {
+                        ghost.step();
+                    }
+                    if world.finished() {
+                        // Only the official daily attempt counts, even when
+                        // its result could not be kept.
+                        let (counts, notes) = if world.mode == Mode::Daily {
+                            report(&mut notice, daily::finish(&world), frames)
+                                .unwrap_or_else(|| (world.official, Vec::new()))
                         } else {
-                            style::Color::Green
-                        },
-                        front: style::Color::Black,
-                        char: logo,
-                    };
+                            (true, Vec::new())
+                        };
+                        let best = counts
+                            && report(&mut notice, submit(&world, &mut scores), frames)
+                                .unwrap_or(false);
+                        report(&mut notice, lifetime.save(), frames);
+                        state = State::Over(menu::over_menu(
+                            world.mode,
+                            world.result(),
+                            best,
+                            world.breakdown(),
+                            best_run(&settings, &physics, &mut notice, frames).is_some(),
+                            world.replay().is_some(),
+                            notes,
+                        ));
+                    }
                 }
+                State::Watching(viewer) => viewer.tick(),
+                State::Paused { .. } | State::Over(_) => {}
             }
         }
-
-        for p in particles.iter_mut() {
-            p.life -= 1;
-
-            if frames % (6 - p.dx.abs()) == 0 {
-                p.p.x += p.dx.signum();
-            }
-            if frames % (6 - p.dy.abs()).max(1) == 0 {
-                p.p.y += p.dy.signum();
-            }
-
-            if p.life % 4 == 0 && p.dy < 3 {
-                p.dy += 1;
-            }
-
-            if p.life < 5 {
-                p.color = match p.color {
-                    style::Color::Yellow => style::Color::DarkYellow,
-                    style::Color::Green => style::Color::DarkGreen,
-                    e => e,
+        stats.ticked(ticks, tick_start.elapsed());
+
+        if ticks > 0 && clock.render() {
+            let render_start = Instant::now();
+            let palette = settings.theme.palette();
+            match &state {
+                State::Title(menu) => {
+                    menu::draw_title(&mut screen, &palette);
+                    draw_particles(&particles, &mut screen, Pos { x: 0, y: 0 });
+                    menu.draw(&mut screen, |i| menu::title_label(&settings, i))
                 }
-            }
-
-            if p.life < 2 {
-                p.color = match p.color {
-                    style::Color::DarkYellow => style::Color::DarkRed,
-                    e => e,
+                State::Scores { stats } => {
+                    menu::draw_title(&mut screen, &palette);
+                    draw_particles(&particles, &mut screen, Pos { x: 0, y: 0 });
+                    if *stats {
+                        menu::draw_stats(&mut screen, &lifetime)
+                    } else {
+                        menu::draw_scores(&mut screen, &scores)
+                    }
                 }
-            }
-
-            if p.p.x < 1 || p.p.x >= W - 1 || p.p.y < 1 || p.p.y >= H - 1 {
-                continue;
-            }
-            let index = (p.p.x + p.p.y * W) as usize;
-            if let Pixel {
-                back: style::Color::Black,
-                front: style::Color::Black,
-                char: ' ',
-            } = pixels[index]
-            {
-                pixels[index] = Pixel {
-                    back: style::Color::Black,
-                    front: p.color,
-                    char: p.kind,
+                State::Playing => {
+                    world.draw(&mut screen, &palette, &sprites);
+                    if let Some(ghost) = &ghost {
+                        if !ghost.world().finished() {
+                            world.draw_ghost(&mut screen, ghost.world(), &sprites);
+                        }
+                    }
+                }
+                State::Paused {
+                    menu, settings: s, ..
+                } => {
+                    world.draw(&mut screen, &palette, &sprites);
+                    match s {
+                        Some(s) => s.draw(&mut screen, |i| menu::setting_label(&settings, i)),
+                        None => menu.draw(&mut screen, menu::pause_label),
+                    }
                 }
+                State::Over(menu) => {
+                    world.draw(&mut screen, &palette, &sprites);
+                    menu.draw(&mut screen, menu::over_label);
+                }
+                State::Watching(viewer) => viewer.draw(&mut screen, &palette, &sprites),
             }
-        }
-        particles.retain(|p| p.life > 0);
-
-        // Queue dirty pixels
-        for (index, (p, pd)) in pixels.iter().zip(pixels_drawn.iter_mut()).enumerate() {
-            if p.back != pd.back || p.front != pd.front || p.char != pd.char {
-                *pd = *p;
-                queue!(
-                    stdout,
-                    cursor::MoveTo((index % W as usize) as u16, (index / W as usize) as u16)
-                )?;
-                queue!(stdout, style::SetForegroundColor(p.front))?;
-                queue!(stdout, style::SetBackgroundColor(p.back))?;
-                queue!(stdout, style::Print(p.char))?;
+            if settings.show_fps {
+                stats.draw(&mut screen, &palette);
             }
+            notice.draw(&mut screen, &palette, frames);
+            screen.flush(stdout)?;
+            stats.rendered(render_start.elapsed());
         }
-        stdout.flush()?;
-        let diff = 1_000_000 / FPS - start.elapsed().as_micros() as i64;
-        if diff > 0 {
-            std::thread::sleep(Duration::from_micros(diff as u64));
+        clock.wait();
+    }
+    audio.finish()?;
+    lifetime.save()?;
+    // A run left in the middle is saved to continue next time, and only
+    // counts once it is left for good. The official daily attempt keeps its
+    // result so far, in case it is never continued.
+    if world.frames() > 0 && !world.finished() {
+        if world.official {
+            daily::finish(&world)?;
         }
+        save::save(&world)
+    } else {
+        retire(&world, &mut scores)
     }
 }
@@ -0,0 +1,476 @@
+use crossterm::{event::KeyCode, style};
+
+use crate::{
+    lifetime::{Achievement, Lifetime},
+    render::{Palette, Pixel, Screen},
+    scores::HighScores,
+    settings::{Mode, Settings, MAX_ENEMIES},
+    store::Choice,
+    world::{format_time, Breakdown},
+    FPS, H, W,
+};
+
+pub enum Nav {
+    Up,
+    Down,
+    Left,
+    Right,
+    Select,
+    Back,
+}
+
+pub fn nav(code: KeyCode) -> Option<Nav> {
+    match code {
+        KeyCode::Up | KeyCode::Char('z') | KeyCode::Char('w') => Some(Nav::Up),
+        KeyCode::Down | KeyCode::Char('s') => Some(Nav::Down),
+        KeyCode::Left | KeyCode::Char('q') | KeyCode::Char('a') => Some(Nav::Left),
+        KeyCode::Right | KeyCode::Char('d') => Some(Nav::Right),
+        KeyCode::Enter | KeyCode::Char(' ') => Some(Nav::Select),
+        KeyCode::Esc | KeyCode::Char('p') => Some(Nav::Back),
+        _ => None,
+    }
+}
+
+pub struct Menu<T> {
+    title: String,
+    /// Lines shown above the items, that cannot be selected.
+    notes: Vec<String>,
+    items: Vec<T>,
+    selected: usize,
+}
+
+impl<T: Copy> Menu<T> {
+    pub fn new(title: impl Into<String>, items: Vec<T>) -> Self {
+        Self {
+            title: title.into(),
+            notes: Vec::new(),
+            items,
+            selected: 0,
+        }
+    }
+
+    pub fn with_notes(mut self, notes: Vec<String>) -> Self {
+        self.notes = notes;
+        self
+    }
+
+    pub fn up(&mut self) {
+        self.selected = (self.selected + self.items.len() - 1) % self.items.len();
+    }
+
+    pub fn down(&mut self) {
+        self.selected = (self.selected + 1) % self.items.len();
+    }
+
+    pub fn current(&self) -> T {
+        self.items[self.selected]
+    }
+
+    pub fn draw(&self, screen: &mut Screen, label: impl Fn(T) -> String) {
+        let mut lines = self.notes.clone();
+        if !lines.is_empty() {
+            lines.push(String::new());
+        }
+        let first = lines.len();
+        lines.extend(self.items.iter().map(|i| label(*i)));
+        draw_box(screen, &self.title, &lines, Some(first + self.selected));
+    }
+}
+
+/// Draws a box in the middle of the screen over whatever is already in the
+/// pixel buffer, with one line highlighted when `selected` is set.
+pub fn draw_box(screen: &mut Screen, title: &str, lines: &[String], selected: Option<usize>) {
+    let inner = lines
+        .iter()
+        .map(|l| l.chars().count())
+        .chain(std::iter::once(title.chars().count()))
+        .max()
+        .unwrap_or(0) as isize;
+    let w = inner + 6;
+    let h = lines.len() as isize + 4;
+    let x0 = W / 2 - w / 2;
+    let y0 = H / 2 - h / 2;
+    for y in y0..y0 + h {
+        for x in x0..x0 + w {
+            let border = x == x0 || x == x0 + w - 1 || y == y0 || y == y0 + h - 1;
+            let back = if border {
+                style::Color::Blue
+            } else {
+                style::Color::Black
+            };
+            screen.put(
+                x,
+                y,
+                Pixel {
+                    back,
+                    front: back,
+                    char: ' ',
+                },
+            );
+        }
+    }
+    screen.print(
+        W / 2 - title.chars().count() as isize / 2,
+        y0 + 1,
+        title,
+        style::Color::Black,
+        style::Color::Yellow,
+    );
+    for (i, l) in lines.iter().enumerate() {
+        let (back, front) = if selected == Some(i) {
+            (style::Color::White, style::Color::Black)
+        } else {
+            (style::Color::Black, style::Color::White)
+        };
+        let padded = format!(" {:<1$} ", l, inner as usize);
+        screen.print(x0 + 2, y0 + 3 + i as isize, &padded, back, front);
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum PauseItem {
+    Resume,
+    Restart,
+    NewSeed,
+    Settings,
+    Title,
+}
+
+pub fn pause_menu() -> Menu<PauseItem> {
+    use PauseItem::*;
+    Menu::new("PAUSED", vec![Resume, Restart, NewSeed, Settings, Title])
+}
+
+pub fn pause_label(item: PauseItem) -> String {
+    match item {
+        PauseItem::Resume => "Resume".into(),
+        PauseItem::Restart => "Restart (same seed)".into(),
+        PauseItem::NewSeed => "Restart (new seed)".into(),
+        PauseItem::Settings => "Settings".into(),
+        PauseItem::Title => "Quit to title".into(),
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum SettingItem {
+    Mode,
+    Level,
+    Difficulty,
+    Enemies,
+    Theme,
+    Controls,
+    Physics,
+    PauseOnFocusLoss,
+    ShowFps,
+    Sound,
+    WideSprites,
+    Back,
+}
+
+/// Settings reachable from the pause menu. The mode and level only change
+/// from the title screen, between runs.
+pub fn settings_menu() -> Menu<SettingItem> {
+    use SettingItem::*;
+    Menu::new(
+        "SETTINGS",
+        vec![
+            Difficulty,
+            Enemies,
+            Theme,
+            Controls,
+            Physics,
+            PauseOnFocusLoss,
+            ShowFps,
+            Sound,
+            WideSprites,
+            Back,
+        ],
+    )
+}
+
+fn value(name: &str, value: impl std::fmt::Display) -> String {
+    format!("{:<20}< {} >", name, value)
+}
+
+pub fn setting_label(settings: &Settings, item: SettingItem) -> String {
+    let on_off = |b| if b { "on" } else { "off" };
+    match item {
+        SettingItem::Mode => value("Mode", settings.mode.label()),
+        SettingItem::Level => value("Level", settings.level.label()),
+        SettingItem::Difficulty => value("Difficulty", settings.difficulty.label()),
+        SettingItem::Enemies => value("Enemies", settings.enemies),
+        SettingItem::Theme => value("Theme", settings.theme.label()),
+        SettingItem::Controls => value("Controls", settings.controls.label()),
+        SettingItem::Physics => value("Physics", settings.physics.label()),
+        SettingItem::PauseOnFocusLoss => {
+            value("Pause on focus loss", on_off(settings.pause_on_focus_loss))
+        }
+        SettingItem::ShowFps => value("FPS overlay", on_off(settings.show_fps)),
+        SettingItem::Sound => value("Sound", settings.sound.label()),
+        SettingItem::WideSprites => value("Wide sprites", on_off(settings.wide_sprites)),
+        SettingItem::Back => "Back".into(),
+    }
+}
+
+/// Changes a setting by one notch, `delta` is -1 or 1.
+pub fn change_setting(settings: &mut Settings, item: SettingItem, delta: isize) {
+    match item {
+        SettingItem::Mode => settings.mode = settings.mode.cycle(delta),
+        SettingItem::Level => settings.level = settings.level.cycle(delta),
+        SettingItem::Difficulty => settings.difficulty = settings.difficulty.cycle(delta),
+        SettingItem::Enemies => {
+            settings.enemies =
+                (settings.enemies as isize + delta).clamp(1, MAX_ENEMIES as isize) as usize
+        }
+        SettingItem::Theme => settings.theme = settings.theme.cycle(delta),
+        SettingItem::Controls => settings.controls = settings.controls.cycle(delta),
+        SettingItem::Physics => settings.physics = settings.physics.cycle(delta),
+        SettingItem::PauseOnFocusLoss => {
+            settings.pause_on_focus_loss = !settings.pause_on_focus_loss
+        }
+        SettingItem::ShowFps => settings.show_fps = !settings.show_fps,
+        SettingItem::Sound => settings.sound = settings.sound.cycle(delta),
+        SettingItem::WideSprites => settings.wide_sprites = !settings.wide_sprites,
+        SettingItem::Back => {}
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum TitleItem {
+    /// Carries on with the run saved when quitting last time.
+    Continue,
+    Play,
+    Setting(SettingItem),
+    Scores,
+    /// Watches the replay of the best run of the mode.
+    Watch,
+    Quit,
+}
+
+pub fn title_menu(saved: bool) -> Menu<TitleItem> {
+    use TitleItem::*;
+    let mut items = vec![
+        Play,
+        Setting(SettingItem::Mode),
+        Setting(SettingItem::Level),
+        Setting(SettingItem::Difficulty),
+        Setting(SettingItem::Enemies),
+        Setting(SettingItem::Theme),
+        Setting(SettingItem::Controls),
+        Setting(SettingItem::Physics),
+        Scores,
+        Watch,
+        Quit,
+    ];
+    if saved {
+        items.insert(0, Continue);
+    }
+    Menu::new("", items)
+}
+
+pub fn title_label(settings: &Settings, item: TitleItem) -> String {
+    match item {
+        TitleItem::Continue => "Continue".into(),
+        TitleItem::Play => "Play".into(),
+        TitleItem::Setting(s) => setting_label(settings, s),
+        TitleItem::Scores => "Scores and stats".into(),
+        TitleItem::Watch => "Watch best run".into(),
+        TitleItem::Quit => "Quit".into(),
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum OverItem {
+    Retry,
+    /// Plays the seed of the best time, against its ghost.
+    Race,
+    NewSeed,
+    Watch,
+    Title,
+}
+
+pub fn over_menu(
+    mode: Mode,
+    result: u64,
+    best: bool,
+    breakdown: &Breakdown,
+    race: bool,
+    watch: bool,
+    extra: Vec<String>,
+) -> Menu<OverItem> {
+    use OverItem::*;
+    let result = format_result(mode, result);
+    let title = if best {
+        format!("{}: {}  NEW BEST!", mode.label(), result)
+    } else {
+        format!("{}: {}", mode.label(), result)
+    };
+    let mut notes = Vec::new();
+    if breakdown.enemies > 0 {
+        for (name, points) in [
+            ("Enemies", breakdown.enemies),
+            ("Air tags", breakdown.air),
+            ("Double jump tags", breakdown.double_jump),
+            ("Dash tags", breakdown.dash),
+            ("Combos", breakdown.combo),
+        ] {
+            notes.push(format!("{:<20}{:>6}", name, points));
+        }
+    }
+    if !extra.is_empty() && !notes.is_empty() {
+        notes.push(String::new());
+    }
+    notes.extend(extra);
+    let mut items = if race {
+        vec![Retry, Race, NewSeed, Title]
+    } else if mode == Mode::Daily {
+        // There is only one level a day.
+        vec![Retry, Title]
+    } else {
+        vec![Retry, NewSeed, Title]
+    };
+    if watch {
+        items.insert(items.len() - 1, Watch);
+    }
+    Menu::new(title, items).with_notes(notes)
+}
+
+pub fn over_label(item: OverItem) -> String {
+    match item {
+        OverItem::Retry => "Retry (same seed)".into(),
+        OverItem::Race => "Race your best".into(),
+        OverItem::NewSeed => "Retry (new seed)".into(),
+        OverItem::Watch => "Watch replay".into(),
+        OverItem::Title => "Quit to title".into(),
+    }
+}
+
+fn format_result(mode: Mode, value: u64) -> String {
+    if mode.lower_is_better() {
+        format!("{}s", format_time(value as isize))
+    } else if mode == Mode::Climb {
+        format!("{} rows", value)
+    } else {
+        format!("{} points", value)
+    }
+}
+
+pub fn draw_scores(screen: &mut Screen, scores: &HighScores) {
+    let mut lines = Vec::new();
+    for mode in Mode::ALL {
+        let best: Vec<String> = scores
+            .best(*mode)
+            .map(|e| format_result(*mode, e.value))
+            .collect();
+        lines.push(format!(
+            "{:<12}{}",
+            mode.label(),
+            if best.is_empty() {
+                "-".into()
+            } else {
+                best.join("  ")
+            }
+        ));
+    }
+    lines.push(String::new());
+    lines.push("Back".into());
+    let selected = lines.len() - 1;
+    draw_box(screen, "HIGH SCORES  >", &lines, Some(selected));
+}
+
+/// Lifetime stats and achievements, the page after the high scores.
+pub fn draw_stats(screen: &mut Screen, stats: &Lifetime) {
+    let seconds = stats.frames / FPS as isize;
+    let mut lines = vec![
+        format!("{:<20}{}", "Tags", stats.tags),
+        format!("{:<20}{}", "Jumps", stats.jumps),
+        format!("{:<20}{}", "Double jumps", stats.double_jumps),
+        format!("{:<20}{}", "Dashes", stats.dashes),
+        format!(
+            "{:<20}{}:{:02}:{:02}",
+            "Time played",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        ),
+        format!("{:<20}x{}", "Longest combo", stats.longest_combo),
+        format!("{:<20}{}", "Highest wave", stats.highest_wave),
+        String::new(),
+    ];
+    for &achievement in Achievement::ALL {
+        let mark = if stats.unlocked.contains(&achievement) {
+            'x'
+        } else {
+            ' '
+        };
+        lines.push(format!(
+            "[{}] {:<15}{}",
+            mark,
+            achievement.label(),
+            achievement.description()
+        ));
+    }
+    lines.push(String::new());
+    lines.push("Back".into());
+    let selected = lines.len() - 1;
+    draw_box(screen, "<  STATS", &lines, Some(selected));
+}
+
+pub fn draw_title(screen: &mut Screen, palette: &Palette) {
+    let pixels = &mut screen.pixels;
+    for y in 0..H {
+        for x in 0..W {
+            let index = (x + y * W) as usize;
+            let border = x == 0 || x == W - 1 || y == 0 || y == H - 1;
+            let mut front = style::Color::Black;
+            let mut back = style::Color::Black;
+            let mut char = ' ';
+            if border {
+                back = palette.wall;
+                front = palette.wall;
+                char = ' ';
+            }
+
+            pixels[index] = Pixel { back, front, char };
+        }
+    }
+    let x = &mut 2_isize;
+    let y = &mut 2_isize;
+    let mut pprint = |x: &mut isize, y: &mut isize, c, back, front| {
+        if c == '\n' {
+            *x = 2;
+            *y += 1;
+            return;
+        }
+        let index = (*x + *y * W) as usize;
+        pixels[index] = Pixel {
+            back,
+            front,
+            char: c,
+        };
+        *x += 1;
+        if *x >= W - 1 {
+            *x = 2;
+            *y += 1;
+        }
+    };
+    let text = r#"
+Stupid terminal game. The goal is to collide with the other characters.
+"#;
+    for c in "yjump ".chars().chain(env!("CARGO_PKG_VERSION").chars()) {
+        pprint(x, y, c, style::Color::Black, style::Color::Green);
+    }
+    for c in text.chars() {
+        pprint(x, y, c, style::Color::Black, style::Color::White);
+    }
+    *y = H - 3;
+    *x = 2;
+    for c in "Jump: up  Stop: down  Dash: double-tap  Pause: P".chars() {
+        pprint(x, y, c, style::Color::Black, style::Color::DarkGrey);
+    }
+    *y = H - 2;
+    *x = 45;
+    for c in "Thomas SIMON <mail@thomassimon.dev".chars() {
+        pprint(x, y, c, style::Color::Black, style::Color::Green);
+    }
+}
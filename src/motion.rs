@@ -0,0 +1,118 @@
+use crossterm::style::Color;
+
+use crate::{
+    render::{Pixel, Screen},
+    sprites::Sprites,
+    world::{Char, Dash, Pos},
+};
+
+/// Ticks a character stays squashed after landing or stretched after
+/// jumping.
+pub const POSE_TICKS: isize = 4;
+/// Ticks an afterimage takes to fade out.
+const AFTERIMAGE_TICKS: isize = 8;
+/// How many cells speed lines reach behind someone.
+const SPEED_LINES: isize = 4;
+
+/// A dashing character as it was on a cell it crossed, fading out. Drawn
+/// with the sprite it had at tick `frames`.
+#[derive(Clone)]
+pub struct Afterimage {
+    pos: Pos,
+    char: Char,
+    frames: isize,
+    age: isize,
+}
+
+/// Leaves afterimages on the cells a dashing character crossed this tick.
+pub fn leave_afterimages(afterimages: &mut Vec<Afterimage>, char: &Char, frames: isize) {
+    if let Dash::Dashing(_) = char.dash {
+        for pos in char.trail().take_while(|p| *p != char.pos) {
+            afterimages.push(Afterimage {
+                pos,
+                char: char.clone(),
+                frames,
+                age: 0,
+            });
+        }
+    }
+}
+
+pub fn update_afterimages(afterimages: &mut Vec<Afterimage>) {
+    for a in afterimages.iter_mut() {
+        a.age += 1;
+    }
+    afterimages.retain(|a| a.age < AFTERIMAGE_TICKS);
+}
+
+pub fn draw_afterimages(
+    afterimages: &[Afterimage],
+    sprites: &Sprites,
+    screen: &mut Screen,
+    origin: Pos,
+) {
+    for a in afterimages {
+        let kind = a.char.kind;
+        let ramp = [kind.color(), kind.trail_color(), Color::DarkGrey];
+        let pixel = Pixel {
+            back: Color::Black,
+            front: ramp[(a.age * ramp.len() as isize / AFTERIMAGE_TICKS) as usize],
+            char: sprites.frame(&a.char, a.frames).0,
+        };
+        screen.put_blank(a.pos.x - origin.x, a.pos.y - origin.y, pixel);
+    }
+}
+
+/// Lines streaming behind someone dashing, or above someone falling fast.
+pub fn draw_speed_lines(char: &Char, falling: bool, screen: &mut Screen, origin: Pos) {
+    let (x, y) = (char.pos.x - origin.x, char.pos.y - origin.y);
+    let line = |char| Pixel {
+        back: Color::Black,
+        front: Color::DarkGrey,
+        char,
+    };
+    if let Dash::Dashing(_) = char.dash {
+        let behind = -char.right_power;
+        for d in 1..=SPEED_LINES {
+            screen.put_blank(x + behind * d, y - 1, line('-'));
+            screen.put_blank(x + behind * (d + 1), y + 1, line('-'));
+        }
+    } else if falling {
+        for d in 1..SPEED_LINES {
+            screen.put_blank(x - 1, y - d, line('|'));
+            screen.put_blank(x + 1, y - d - 1, line('|'));
+        }
+    }
+}
+
+/// The glyph of a character squashed flat after landing.
+pub fn squashed(glyph: char) -> char {
+    match glyph {
+        '>' => '›',
+        '<' => '‹',
+        'Y' => 'v',
+        c => c.to_ascii_lowercase(),
+    }
+}
+
+/// Widens a character who just landed, or makes one who just jumped a cell
+/// taller, around the cell it is drawn on.
+pub fn draw_pose(char: &Char, screen: &mut Screen, origin: Pos) {
+    let (x, y) = (char.pos.x - origin.x, char.pos.y - origin.y);
+    if char.pose > 0 {
+        let flat = Pixel {
+            back: Color::Black,
+            front: char.kind.color(),
+            char: '_',
+        };
+        screen.put_blank(x - 1, y, flat);
+        screen.put_blank(x + 1, y, flat);
+    } else if char.pose < 0 {
+        let tail = Pixel {
+            back: char.kind.trail_color(),
+            front: Color::Black,
+            char: ' ',
+        };
+        screen.put_blank(x, y + 1, tail);
+    }
+}
@@ -0,0 +1,385 @@
+use crossterm::style::Color;
+
+use crate::{
+    render::{Pixel, Screen, BLANK},
+    save::{color_key, parse_color, Fields},
+    store::Choice,
+    world::{Pos, Rand, SUB},
+    H, W,
+};
+
+/// Most particles alive at once, the oldest go first past it.
+const MAX_PARTICLES: usize = 300;
+
+/// How an emitter throws its particles.
+#[derive(Clone, Copy)]
+pub enum Emitter {
+    /// Every direction at random speeds.
+    Burst,
+    /// Evenly around a circle, all at the same speed.
+    Ring,
+    /// Upwards, fast, to fall back down.
+    Sparks,
+    /// Left in place, one per tick while something moves.
+    Trail,
+}
+
+/// Everything about how an effect looks and moves. Speeds are in
+/// sub-cells per tick.
+pub struct ParticleProfile {
+    emitter: Emitter,
+    count: usize,
+    speed: isize,
+    /// Added to the vertical speed every tick.
+    gravity: isize,
+    /// Sixteenths of the speed lost every tick.
+    drag: isize,
+    /// Lifetime range in ticks, inclusive.
+    life: (isize, isize),
+    glyphs: &'static [char],
+    /// Colors from birth to death. Empty uses the color given when
+    /// emitting, dimmed at the end.
+    ramp: &'static [Color],
+}
+
+/// The effects of the game.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Effect {
+    /// Kicked up when taking off or landing.
+    Dust,
+    /// Behind someone dashing.
+    Trail,
+    /// Where an enemy was tagged.
+    Explosion,
+    /// Off someone stomped or knocked back.
+    Sparks,
+    /// Where a teleporter left and showed up.
+    Teleport,
+    /// Floating around the title screen.
+    Ambience,
+}
+
+impl Choice for Effect {
+    const ALL: &'static [Self] = &[
+        Effect::Dust,
+        Effect::Trail,
+        Effect::Explosion,
+        Effect::Sparks,
+        Effect::Teleport,
+        Effect::Ambience,
+    ];
+
+    fn key(self) -> &'static str {
+        match self {
+            Effect::Dust => "dust",
+            Effect::Trail => "trail",
+            Effect::Explosion => "explosion",
+            Effect::Sparks => "sparks",
+            Effect::Teleport => "teleport",
+            Effect::Ambience => "ambience",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        self.key()
+    }
+}
+
+impl Effect {
+    pub fn profile(self) -> ParticleProfile {
+        match self {
+            Effect::Dust => ParticleProfile {
+                emitter: Emitter::Burst,
+                count: 5,
+                speed: SUB / 5,
+                gravity: SUB / 64,
+                drag: 3,
+                life: (8, 16),
+                glyphs: &['.', '·', '\'', ','],
+                ramp: &[],
+            },
+            Effect::Trail => ParticleProfile {
+                emitter: Emitter::Trail,
+                count: 1,
+                speed: 0,
+                gravity: 0,
+                drag: 0,
+                life: (6, 10),
+                glyphs: &['·', '-', '~'],
+                ramp: &[],
+            },
+            Effect::Explosion => ParticleProfile {
+                emitter: Emitter::Ring,
+                count: 12,
+                speed: SUB / 3,
+                gravity: SUB / 96,
+                drag: 2,
+                life: (12, 24),
+                glyphs: &['*', '¤', '+', '°'],
+                ramp: &[],
+            },
+            Effect::Sparks => ParticleProfile {
+                emitter: Emitter::Sparks,
+                count: 8,
+                speed: SUB / 3,
+                gravity: SUB / 32,
+                drag: 1,
+                life: (6, 14),
+                glyphs: &['\'', '`', '.', '*'],
+                ramp: &[
+                    Color::White,
+                    Color::Yellow,
+                    Color::DarkYellow,
+                    Color::DarkRed,
+                ],
+            },
+            Effect::Teleport => ParticleProfile {
+                emitter: Emitter::Ring,
+                count: 8,
+                speed: SUB / 4,
+                gravity: 0,
+                drag: 4,
+                life: (8, 12),
+                glyphs: &['°', 'o', '.'],
+                ramp: &[],
+            },
+            Effect::Ambience => ParticleProfile {
+                emitter: Emitter::Burst,
+                count: 5,
+                speed: SUB / 4,
+                gravity: SUB / 80,
+                drag: 1,
+                life: (10, 19),
+                glyphs: &['*', '.', '¨', '¤', '\'', '²', '·'],
+                ramp: &[],
+            },
+        }
+    }
+}
+
+/// The darker shade of a color, for particles dying out.
+fn dim(color: Color) -> Color {
+    match color {
+        Color::White => Color::Grey,
+        Color::Grey => Color::DarkGrey,
+        Color::Red => Color::DarkRed,
+        Color::Green => Color::DarkGreen,
+        Color::Yellow => Color::DarkYellow,
+        Color::Blue => Color::DarkBlue,
+        Color::Magenta => Color::DarkMagenta,
+        Color::Cyan => Color::DarkCyan,
+        Color::DarkYellow => Color::DarkRed,
+        c => c,
+    }
+}
+
+#[derive(Clone)]
+pub struct Particle {
+    /// In sub-cells.
+    x: isize,
+    y: isize,
+    vx: isize,
+    vy: isize,
+    age: isize,
+    life: isize,
+    effect: Effect,
+    glyph: char,
+    color: Color,
+}
+
+impl Particle {
+    fn color(&self) -> Color {
+        let ramp = self.effect.profile().ramp;
+        if ramp.is_empty() {
+            if self.age * 3 > self.life * 2 {
+                dim(self.color)
+            } else {
+                self.color
+            }
+        } else {
+            ramp[((self.age * ramp.len() as isize / self.life) as usize).min(ramp.len() - 1)]
+        }
+    }
+
+    /// As a line of the save file.
+    pub fn to_save(&self) -> String {
+        format!(
+            "{} {} {} {} {} {} {} {} {}",
+            self.x,
+            self.y,
+            self.vx,
+            self.vy,
+            self.age,
+            self.life,
+            self.effect.key(),
+            self.glyph,
+            color_key(self.color)
+        )
+    }
+
+    pub fn from_save(mut f: Fields) -> Result<Self, String> {
+        Ok(Particle {
+            x: f.next()?,
+            y: f.next()?,
+            vx: f.next()?,
+            vy: f.next()?,
+            age: f.next()?,
+            life: f.next()?,
+            effect: Effect::parse(f.word()?)?,
+            glyph: f.next()?,
+            color: parse_color(f.word()?)?,
+        })
+    }
+}
+
+/// A random value from `-max` to `max`.
+fn spread(rand: &mut Rand, max: isize) -> isize {
+    (rand.next() % (2 * max + 1) as usize) as isize - max
+}
+
+/// Emits an effect from the middle of cell `pos`. `color` is for effects
+/// without colors of their own.
+pub fn emit(
+    particles: &mut Vec<Particle>,
+    effect: Effect,
+    pos: Pos,
+    rand: &mut Rand,
+    color: Color,
+) {
+    let profile = effect.profile();
+    let s = profile.speed;
+    for i in 0..profile.count {
+        let (vx, vy) = match profile.emitter {
+            Emitter::Burst => (spread(rand, s), spread(rand, s / 2) - s / 2),
+            Emitter::Ring => {
+                let angle = std::f64::consts::TAU * i as f64 / profile.count as f64;
+                // Cells are about twice as tall as they are wide.
+                (
+                    (s as f64 * angle.cos()) as isize,
+                    (s as f64 * angle.sin() / 2.0) as isize,
+                )
+            }
+            Emitter::Sparks => (
+                spread(rand, s / 2),
+                -s / 2 - (rand.next() % s as usize) as isize,
+            ),
+            Emitter::Trail => (0, 0),
+        };
+        let (min, max) = profile.life;
+        particles.push(Particle {
+            x: pos.x * SUB + SUB / 2,
+            y: pos.y * SUB + SUB / 2,
+            vx,
+            vy,
+            age: 0,
+            life: min + (rand.next() % (max - min + 1) as usize) as isize,
+            effect,
+            glyph: profile.glyphs[rand.next() % profile.glyphs.len()],
+            color,
+        });
+    }
+    if particles.len() > MAX_PARTICLES {
+        particles.drain(..particles.len() - MAX_PARTICLES);
+    }
+}
+
+pub fn update_particles(particles: &mut Vec<Particle>) {
+    for p in particles.iter_mut() {
+        let profile = p.effect.profile();
+        p.age += 1;
+        p.x += p.vx;
+        p.y += p.vy;
+        p.vx -= p.vx * profile.drag / 16;
+        p.vy -= p.vy * profile.drag / 16;
+        p.vy += profile.gravity;
+    }
+    particles.retain(|p| p.age < p.life);
+}
+
+/// A line of text floating up from where something happened.
+#[derive(Clone)]
+pub struct Popup {
+    pos: Pos,
+    text: String,
+    life: isize,
+    color: Color,
+}
+
+impl Popup {
+    /// As a line of the save file, the text last as it has spaces.
+    pub fn to_save(&self) -> String {
+        format!(
+            "{} {} {} {} {}",
+            self.pos.x,
+            self.pos.y,
+            self.life,
+            color_key(self.color),
+            self.text
+        )
+    }
+
+    pub fn from_save(mut f: Fields) -> Result<Self, String> {
+        Ok(Popup {
+            pos: Pos {
+                x: f.next()?,
+                y: f.next()?,
+            },
+            life: f.next()?,
+            color: parse_color(f.word()?)?,
+            text: f.rest(),
+        })
+    }
+}
+
+pub fn spawn_popup(popups: &mut Vec<Popup>, pos: Pos, text: String, color: Color) {
+    popups.push(Popup {
+        pos,
+        text,
+        life: 60,
+        color,
+    });
+}
+
+pub fn update_popups(popups: &mut Vec<Popup>) {
+    for p in popups.iter_mut() {
+        p.life -= 1;
+        if p.life % 12 == 0 {
+            p.pos.y -= 1;
+        }
+    }
+    popups.retain(|p| p.life > 0);
+}
+
+/// Popups show up over everything, centered on where they started.
+pub fn draw_popups(popups: &[Popup], screen: &mut Screen, origin: Pos) {
+    for p in popups {
+        let x = p.pos.x - origin.x - p.text.chars().count() as isize / 2;
+        let x = x.clamp(1, (W - 1 - p.text.chars().count() as isize).max(1));
+        let y = p.pos.y - origin.y - 1;
+        if (1..H - 1).contains(&y) {
+            screen.print(x, y, &p.text, Color::Black, p.color);
+        }
+    }
+}
+
+/// Particles only show up on empty cells. `origin` is the level cell in the
+/// top left corner of the screen.
+pub fn draw_particles(particles: &[Particle], screen: &mut Screen, origin: Pos) {
+    for p in particles {
+        let (x, y) = (
+            p.x.div_euclid(SUB) - origin.x,
+            p.y.div_euclid(SUB) - origin.y,
+        );
+        if !(1..W - 1).contains(&x) || !(1..H - 1).contains(&y) {
+            continue;
+        }
+        let index = (x + y * W) as usize;
+        if screen.pixels[index] == BLANK {
+            screen.pixels[index] = Pixel {
+                back: Color::Black,
+                front: p.color(),
+                char: p.glyph,
+            }
+        }
+    }
+}
@@ -0,0 +1,260 @@
+use std::time::SystemTime;
+
+use crate::{error::Error, store, store::Choice, world::SUB};
+
+const FILE: &str = "physics";
+
+/// Everything that decides how characters move. Speeds are in 1/SUB of a
+/// cell per tick, durations in ticks.
+#[derive(Clone, PartialEq, Eq)]
+pub struct PhysicsProfile {
+    pub gravity: isize,
+    pub max_fall: isize,
+    pub jump: isize,
+    pub run_jump: isize,
+    pub run: isize,
+    pub dash_speed: isize,
+    pub dash_length: usize,
+    pub dash_cooldown: usize,
+    /// Fastest fall while pressing into a wall.
+    pub wall_slide: isize,
+    pub wall_jump: isize,
+    /// Longest gap between two presses of a direction that still dashes.
+    pub double_tap: isize,
+    /// How long a jump press is remembered before landing.
+    pub jump_buffer: i32,
+    /// How long a stomped character cannot move.
+    pub stun: usize,
+    /// Speed given to someone dashed into.
+    pub knockback: isize,
+    /// Speed two characters bump each other away with.
+    pub bounce: isize,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Preset {
+    Classic,
+    Floaty,
+    Heavy,
+    Speedrun,
+    /// Read from the physics file, and reloaded when it changes.
+    Custom,
+}
+
+impl Choice for Preset {
+    const ALL: &'static [Self] = &[
+        Preset::Classic,
+        Preset::Floaty,
+        Preset::Heavy,
+        Preset::Speedrun,
+        Preset::Custom,
+    ];
+
+    fn key(self) -> &'static str {
+        match self {
+            Preset::Classic => "classic",
+            Preset::Floaty => "floaty",
+            Preset::Heavy => "heavy",
+            Preset::Speedrun => "speedrun",
+            Preset::Custom => "custom",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Preset::Classic => "Classic",
+            Preset::Floaty => "Floaty",
+            Preset::Heavy => "Heavy",
+            Preset::Speedrun => "Speedrun",
+            Preset::Custom => "Custom (file)",
+        }
+    }
+}
+
+impl Preset {
+    pub fn load(self) -> Result<PhysicsProfile, Error> {
+        match self {
+            Preset::Custom => PhysicsProfile::load(),
+            preset => Ok(PhysicsProfile::preset(preset)),
+        }
+    }
+}
+
+impl PhysicsProfile {
+    /// The profile of a built-in preset, classic for `Custom`.
+    pub fn preset(preset: Preset) -> Self {
+        let classic = PhysicsProfile {
+            gravity: 13,
+            max_fall: SUB,
+            jump: 252,
+            run_jump: 210,
+            run: 85,
+            dash_speed: SUB,
+            dash_length: 20,
+            dash_cooldown: 60,
+            wall_slide: 48,
+            wall_jump: 210,
+            double_tap: 20,
+            jump_buffer: 10,
+            stun: 90,
+            knockback: SUB / 2,
+            bounce: 128,
+        };
+        match preset {
+            Preset::Classic | Preset::Custom => classic,
+            Preset::Floaty => PhysicsProfile {
+                gravity: 8,
+                max_fall: SUB * 5 / 8,
+                jump: 210,
+                run_jump: 180,
+                run: 80,
+                dash_length: 24,
+                dash_cooldown: 50,
+                wall_slide: 32,
+                wall_jump: 180,
+                ..classic
+            },
+            Preset::Heavy => PhysicsProfile {
+                gravity: 20,
+                jump: 300,
+                run_jump: 255,
+                dash_length: 14,
+                dash_cooldown: 80,
+                wall_slide: 96,
+                wall_jump: 255,
+                ..classic
+            },
+            Preset::Speedrun => PhysicsProfile {
+                gravity: 16,
+                jump: 290,
+                run_jump: 250,
+                run: 128,
+                dash_length: 24,
+                dash_cooldown: 30,
+                wall_slide: 64,
+                wall_jump: 250,
+                double_tap: 25,
+                jump_buffer: 12,
+                ..classic
+            },
+        }
+    }
+
+    /// Reads the physics file. It may start from a preset with `base = ...`
+    /// and only override some values. A missing file is created from the
+    /// classic preset, as a starting point to tune from.
+    pub fn load() -> Result<Self, Error> {
+        let Some(text) = store::read(FILE)? else {
+            let profile = PhysicsProfile::preset(Preset::Classic);
+            store::write(FILE, &profile.to_text(Preset::Classic))?;
+            return Ok(profile);
+        };
+        PhysicsProfile::parse(FILE, &text)
+    }
+
+    pub fn parse(name: &str, text: &str) -> Result<Self, Error> {
+        let mut profile = PhysicsProfile::preset(Preset::Classic);
+        store::parse(name, text, |key, value| {
+            if key == "base" {
+                profile = PhysicsProfile::preset(Preset::parse(value)?);
+                return Ok(());
+            }
+            profile.set(key, value)
+        })?;
+        Ok(profile)
+    }
+
+    /// Changes one value by its name in the physics file.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let n: isize = value
+            .parse()
+            .map_err(|_| format!("`{}` is not a number", value))?;
+        let positive = |n: isize| {
+            if n > 0 {
+                Ok(n)
+            } else {
+                Err(format!("{} must be positive", key))
+            }
+        };
+        match key {
+            "gravity" => self.gravity = positive(n)?,
+            "max_fall" => self.max_fall = positive(n)?,
+            "jump" => self.jump = positive(n)?,
+            "run_jump" => self.run_jump = positive(n)?,
+            "run" => self.run = n.max(0),
+            "dash_speed" => self.dash_speed = n.max(0),
+            "dash_length" => self.dash_length = n.max(0) as usize,
+            "dash_cooldown" => self.dash_cooldown = n.max(0) as usize,
+            "wall_slide" => self.wall_slide = positive(n)?,
+            "wall_jump" => self.wall_jump = positive(n)?,
+            "double_tap" => self.double_tap = n.max(0),
+            "jump_buffer" => self.jump_buffer = positive(n)? as i32,
+            "stun" => self.stun = n.max(0) as usize,
+            "knockback" => self.knockback = n.max(0),
+            "bounce" => self.bounce = n.max(0),
+            _ => return Err(format!("unknown physics value `{}`", key)),
+        }
+        Ok(())
+    }
+
+    /// Every value with its name in the physics file.
+    pub fn values(&self) -> [(&'static str, isize); 15] {
+        [
+            ("gravity", self.gravity),
+            ("max_fall", self.max_fall),
+            ("jump", self.jump),
+            ("run_jump", self.run_jump),
+            ("run", self.run),
+            ("dash_speed", self.dash_speed),
+            ("dash_length", self.dash_length as isize),
+            ("dash_cooldown", self.dash_cooldown as isize),
+            ("wall_slide", self.wall_slide),
+            ("wall_jump", self.wall_jump),
+            ("double_tap", self.double_tap),
+            ("jump_buffer", self.jump_buffer as isize),
+            ("stun", self.stun as isize),
+            ("knockback", self.knockback),
+            ("bounce", self.bounce),
+        ]
+    }
+
+    pub fn to_text(&self, base: Preset) -> String {
+        let mut text = format!(
+            "# Speeds in 1/{} of a cell per tick, durations in ticks (60 per second).\n\
+             base = {}\n",
+            SUB,
+            base.key(),
+        );
+        for (key, value) in self.values() {
+            text += &format!("{} = {}\n", key, value);
+        }
+        text
+    }
+}
+
+/// Notices changes to the physics file, for tuning while playing.
+pub struct Watcher {
+    modified: Option<SystemTime>,
+}
+
+impl Watcher {
+    pub fn new() -> Self {
+        Self {
+            modified: Self::modified(),
+        }
+    }
+
+    fn modified() -> Option<SystemTime> {
+        store::path(FILE)
+            .and_then(|p| std::fs::metadata(p).ok())
+            .and_then(|m| m.modified().ok())
+    }
+
+    /// Whether the file changed since the last call.
+    pub fn changed(&mut self) -> bool {
+        let modified = Self::modified();
+        let changed = modified != self.modified;
+        self.modified = modified;
+        changed
+    }
+}
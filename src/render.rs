@@ -0,0 +1,140 @@
+use std::io::{Stdout, Write};
+
+use crossterm::{cursor, queue, style};
+
+use crate::{H, W};
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct Pixel {
+    pub back: style::Color,
+    pub front: style::Color,
+    pub char: char,
+}
+
+/// Colors that change with the theme.
+#[derive(Clone, Copy)]
+pub struct Palette {
+    pub wall: style::Color,
+    pub solid: style::Color,
+    pub hud: style::Color,
+}
+
+pub const BLANK: Pixel = Pixel {
+    back: style::Color::Black,
+    front: style::Color::Black,
+    char: ' ',
+};
+
+// Never printed, so every cell is dirty after an invalidation.
+const STALE: Pixel = Pixel {
+    back: style::Color::Black,
+    front: style::Color::Black,
+    char: '£',
+};
+
+/// A 5 dots high font for banners, with what they need to say.
+fn big_glyph(c: char) -> [&'static str; 5] {
+    match c {
+        'W' => ["#...#", "#...#", "#.#.#", "##.##", "#...#"],
+        'A' => [".#.", "#.#", "###", "#.#", "#.#"],
+        'V' => ["#.#", "#.#", "#.#", "#.#", ".#."],
+        'E' => ["###", "#..", "##.", "#..", "###"],
+        '0' => ["###", "#.#", "#.#", "#.#", "###"],
+        '1' => [".#.", "##.", ".#.", ".#.", "###"],
+        '2' => ["###", "..#", "###", "#..", "###"],
+        '3' => ["###", "..#", ".##", "..#", "###"],
+        '4' => ["#.#", "#.#", "###", "..#", "..#"],
+        '5' => ["###", "#..", "###", "..#", "###"],
+        '6' => ["###", "#..", "###", "#.#", "###"],
+        '7' => ["###", "..#", "..#", ".#.", ".#."],
+        '8' => ["###", "#.#", "###", "#.#", "###"],
+        '9' => ["###", "#.#", "###", "..#", "###"],
+        _ => ["..", "..", "..", "..", ".."],
+    }
+}
+
+pub struct Screen {
+    pub pixels: [Pixel; (W * H) as usize],
+    drawn: [Pixel; (W * H) as usize],
+}
+
+impl Screen {
+    pub fn new() -> Self {
+        Self {
+            pixels: [BLANK; (W * H) as usize],
+            drawn: [STALE; (W * H) as usize],
+        }
+    }
+
+    /// Forces a full redraw on the next flush, after a resize or anything
+    /// else that may have scribbled over the terminal.
+    pub fn invalidate(&mut self) {
+        self.drawn = [STALE; (W * H) as usize];
+    }
+
+    pub fn put(&mut self, x: isize, y: isize, pixel: Pixel) {
+        if (0..W).contains(&x) && (0..H).contains(&y) {
+            self.pixels[(x + y * W) as usize] = pixel;
+        }
+    }
+
+    /// Like `put`, but only over empty cells.
+    pub fn put_blank(&mut self, x: isize, y: isize, pixel: Pixel) {
+        if (0..W).contains(&x) && (0..H).contains(&y) && self.pixels[(x + y * W) as usize] == BLANK
+        {
+            self.pixels[(x + y * W) as usize] = pixel;
+        }
+    }
+
+    pub fn print(&mut self, x: isize, y: isize, s: &str, back: style::Color, front: style::Color) {
+        for (x, char) in (x..).zip(s.chars()) {
+            self.put(x, y, Pixel { back, front, char });
+        }
+    }
+
+    /// Prints `text` in big letters, centered, with each lit dot of the
+    /// font two cells wide and tall.
+    pub fn banner(&mut self, y: isize, text: &str, color: style::Color) {
+        let glyphs: Vec<_> = text.chars().map(big_glyph).collect();
+        let width: isize = glyphs
+            .iter()
+            .map(|g| g[0].len() as isize + 1)
+            .sum::<isize>()
+            - 1;
+        let mut x = W / 2 - width;
+        let pixel = Pixel {
+            back: color,
+            front: color,
+            char: ' ',
+        };
+        for glyph in glyphs {
+            for (dy, row) in (0..).zip(glyph) {
+                for (dx, dot) in (0..).zip(row.chars()) {
+                    if dot == '#' {
+                        for (sx, sy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                            self.put(x + dx * 2 + sx, y + dy * 2 + sy, pixel);
+                        }
+                    }
+                }
+            }
+            x += (glyph[0].len() as isize + 1) * 2;
+        }
+    }
+
+    pub fn flush(&mut self, stdout: &mut Stdout) -> std::io::Result<()> {
+        // Queue dirty pixels
+        for (index, (p, pd)) in self.pixels.iter().zip(self.drawn.iter_mut()).enumerate() {
+            if p != pd {
+                *pd = *p;
+                queue!(
+                    stdout,
+                    cursor::MoveTo((index % W as usize) as u16, (index / W as usize) as u16)
+                )?;
+                queue!(stdout, style::SetForegroundColor(p.front))?;
+                queue!(stdout, style::SetBackgroundColor(p.back))?;
+                queue!(stdout, style::Print(p.char))?;
+            }
+        }
+        stdout.flush()
+    }
+}
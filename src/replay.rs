@@ -0,0 +1,223 @@
+use std::{fs, path::Path, rc::Rc};
+
+use crate::{
+    error::Error,
+    physics::PhysicsProfile,
+    settings::{Difficulty, Level, Mode, Settings},
+    store::{self, Choice},
+    world::{Action, World},
+};
+
+/// Version of the simulation replays are recorded with. Anything that
+/// changes how a run plays out must bump it, as older replays would no
+/// longer play the same.
+pub const VERSION: u32 = 5;
+
+/// A run as what it started from and the inputs that made it. The world
+/// only depends on those, so playing the inputs back at the same ticks
+/// gives the same run again.
+#[derive(Clone)]
+pub struct Replay {
+    pub version: u32,
+    pub seed: usize,
+    pub mode: Mode,
+    pub level: Level,
+    pub difficulty: Difficulty,
+    pub enemies: usize,
+    pub physics: PhysicsProfile,
+    /// Each input with the tick it was pressed after.
+    pub inputs: Vec<(isize, Action)>,
+    /// How the run ended, as claimed by whoever recorded it.
+    pub score: usize,
+    pub frames: isize,
+}
+
+impl Replay {
+    /// The settings the run was played with, the others left as default.
+    pub fn settings(&self) -> Settings {
+        Settings {
+            mode: self.mode,
+            level: self.level,
+            difficulty: self.difficulty,
+            enemies: self.enemies,
+            ..Settings::default()
+        }
+    }
+
+    /// Whether the run was played with these settings and physics.
+    pub fn fits(&self, settings: &Settings, physics: &PhysicsProfile) -> bool {
+        self.mode == settings.mode
+            && self.level == settings.level
+            && self.difficulty == settings.difficulty
+            && self.enemies == settings.enemies
+            && self.physics == *physics
+    }
+
+    /// The replay kept under `name`, unless it was recorded with another
+    /// version. Those are of no use to play against and may not even parse
+    /// any more, so only their version is read.
+    pub fn load(name: &str) -> Result<Option<Self>, Error> {
+        let Some(text) = store::read(name)? else {
+            return Ok(None);
+        };
+        let mut version = None;
+        store::parse(name, &text, |key, value| {
+            if key == "version" {
+                version = value.parse().ok();
+            }
+            Ok(())
+        })?;
+        if version != Some(VERSION) {
+            return Ok(None);
+        }
+        Replay::parse(name, &text).map(Some)
+    }
+
+    pub fn save(&self, name: &str) -> Result<(), Error> {
+        store::write(name, &self.to_text())
+    }
+
+    /// Reads a replay file from anywhere, given on the command line. A bare
+    /// name that is not in the current directory is looked up with the
+    /// stored files, where best runs and daily replays are kept.
+    pub fn read(path: &Path) -> Result<Self, Error> {
+        let stored = match path.to_str() {
+            Some(name) if !path.exists() && path.components().count() == 1 => {
+                store::path(name).filter(|p| p.exists())
+            }
+            _ => None,
+        };
+        let path = std::path::absolute(stored.as_deref().unwrap_or(path))?;
+        let text = fs::read_to_string(&path)
+            .map_err(|e| Error::Config(format!("{}: {}", path.display(), e)))?;
+        Replay::parse(&path.display().to_string(), &text)
+    }
+
+    /// Whether this version of the simulation plays the replay as it was
+    /// recorded.
+    pub fn playable(&self) -> Result<(), String> {
+        if self.version == VERSION {
+            Ok(())
+        } else {
+            Err(format!(
+                "recorded with version {}, this yjump plays version {}",
+                self.version, VERSION
+            ))
+        }
+    }
+
+    pub fn parse(name: &str, text: &str) -> Result<Self, Error> {
+        let defaults = Settings::default();
+        let mut replay = Replay {
+            version: 0,
+            seed: 0,
+            mode: defaults.mode,
+            level: defaults.level,
+            difficulty: defaults.difficulty,
+            enemies: defaults.enemies,
+            physics: PhysicsProfile::preset(defaults.physics),
+            inputs: Vec::new(),
+            score: 0,
+            frames: 0,
+        };
+        store::parse(name, text, |key, value| {
+            match key {
+                "version" => replay.version = value.parse().map_err(|_| "expected a number")?,
+                "seed" => replay.seed = value.parse().map_err(|_| "expected a number")?,
+                "mode" => replay.mode = Mode::parse(value)?,
+                "level" => replay.level = Level::parse(value)?,
+                "difficulty" => replay.difficulty = Difficulty::parse(value)?,
+                "enemies" => replay.enemies = value.parse().map_err(|_| "expected a number")?,
+                "score" => replay.score = value.parse().map_err(|_| "expected a number")?,
+                "frames" => replay.frames = value.parse().map_err(|_| "expected a number")?,
+                "input" => {
+                    let input = value.split_once(' ').and_then(|(tick, action)| {
+                        Some((tick.parse().ok()?, Action::parse(action.trim())?))
+                    });
+                    replay
+                        .inputs
+                        .push(input.ok_or("expected `input = <tick> <action>`")?);
+                }
+                _ => match key.strip_prefix("physics.") {
+                    Some(key) => replay.physics.set(key, value)?,
+                    None => return Err(format!("unknown replay value `{}`", key)),
+                },
+            }
+            Ok(())
+        })?;
+        Ok(replay)
+    }
+
+    /// A fingerprint of the replay, for results to point at it.
+    pub fn hash(&self) -> u64 {
+        hash(self.to_text().as_bytes())
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = format!(
+            "version = {}\nseed = {}\nmode = {}\nlevel = {}\ndifficulty = {}\nenemies = {}\n\
+             score = {}\nframes = {}\n",
+            self.version,
+            self.seed,
+            self.mode.key(),
+            self.level.key(),
+            self.difficulty.key(),
+            self.enemies,
+            self.score,
+            self.frames,
+        );
+        for (key, value) in self.physics.values() {
+            text += &format!("physics.{} = {}\n", key, value);
+        }
+        for (tick, action) in &self.inputs {
+            text += &format!("input = {} {}\n", tick, action.key());
+        }
+        text
+    }
+}
+
+/// FNV-1a, short and stable across platforms and versions.
+pub fn hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |h, b| {
+        (h ^ *b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// A replay being played back, one tick at a time. Cloning it is a
+/// snapshot to play on from.
+#[derive(Clone)]
+pub struct Playback {
+    world: World,
+    inputs: Rc<[(isize, Action)]>,
+    next: usize,
+}
+
+impl Playback {
+    pub fn new(replay: &Replay) -> Self {
+        Self {
+            world: World::new(replay.seed, &replay.settings(), &replay.physics),
+            inputs: replay.inputs.as_slice().into(),
+            next: 0,
+        }
+    }
+
+    /// Presses what was pressed after the current tick, then steps. Stops
+    /// once the run is over.
+    pub fn step(&mut self) {
+        if self.world.finished() {
+            return;
+        }
+        while let Some(&(tick, action)) = self.inputs.get(self.next) {
+            if tick > self.world.frames() {
+                break;
+            }
+            self.world.press(action);
+            self.next += 1;
+        }
+        self.world.step();
+    }
+
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+}
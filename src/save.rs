@@ -0,0 +1,158 @@
+use std::str::{FromStr, SplitWhitespace};
+
+use crossterm::style::Color;
+
+use crate::{error::Error, store, world::World};
+
+const FILE: &str = "save";
+
+/// Version of the save format. Anything that changes what is saved, or how
+/// the world plays on from it, must bump it so older saves are turned down
+/// instead of resuming into something else.
+pub const VERSION: u32 = 7;
+
+/// Whether there is a run to continue.
+pub fn exists() -> bool {
+    store::path(FILE).is_some_and(|p| p.exists())
+}
+
+/// Saves a run left in the middle, to continue it next time.
+pub fn save(world: &World) -> Result<(), Error> {
+    let text = format!(
+        "# A run left in the middle, continued from the title screen.\nversion = {}\n{}",
+        VERSION,
+        world.to_save()
+    );
+    store::write(FILE, &text)
+}
+
+/// Takes the saved run out of its file, so that it is only continued once.
+/// Saves of another version are thrown away with an error.
+pub fn take() -> Result<Option<World>, Error> {
+    let Some(text) = store::read(FILE)? else {
+        return Ok(None);
+    };
+    store::remove(FILE)?;
+    let mut version = 0;
+    store::parse(FILE, &text, |key, value| {
+        if key == "version" {
+            version = value.parse().map_err(|_| "expected a number")?;
+        }
+        Ok(())
+    })?;
+    if version != VERSION {
+        return Err(Error::Config(format!(
+            "the saved run has format {} instead of {}, it was dropped",
+            version, VERSION
+        )));
+    }
+    World::from_save(FILE, &text).map(Some)
+}
+
+/// The values of a saved line, read in order.
+pub struct Fields<'a>(SplitWhitespace<'a>);
+
+impl<'a> Fields<'a> {
+    pub fn new(line: &'a str) -> Self {
+        Fields(line.split_whitespace())
+    }
+
+    pub fn word(&mut self) -> Result<&'a str, String> {
+        self.0.next().ok_or_else(|| "missing value".into())
+    }
+
+    pub fn next<T: FromStr>(&mut self) -> Result<T, String> {
+        let word = self.word()?;
+        word.parse().map_err(|_| format!("bad value `{}`", word))
+    }
+
+    /// Everything left, words joined by single spaces.
+    pub fn rest(self) -> String {
+        self.0.collect::<Vec<_>>().join(" ")
+    }
+}
+
+const COLORS: [(Color, &str); 17] = [
+    (Color::Reset, "reset"),
+    (Color::Black, "black"),
+    (Color::DarkGrey, "dark_grey"),
+    (Color::Red, "red"),
+    (Color::DarkRed, "dark_red"),
+    (Color::Green, "green"),
+    (Color::DarkGreen, "dark_green"),
+    (Color::Yellow, "yellow"),
+    (Color::DarkYellow, "dark_yellow"),
+    (Color::Blue, "blue"),
+    (Color::DarkBlue, "dark_blue"),
+    (Color::Magenta, "magenta"),
+    (Color::DarkMagenta, "dark_magenta"),
+    (Color::Cyan, "cyan"),
+    (Color::DarkCyan, "dark_cyan"),
+    (Color::White, "white"),
+    (Color::Grey, "grey"),
+];
+
+pub fn color_key(color: Color) -> String {
+    match color {
+        Color::Rgb { r, g, b } => format!("#{:02x}{:02x}{:02x}", r, g, b),
+        Color::AnsiValue(n) => format!("ansi:{}", n),
+        color => COLORS
+            .iter()
+            .find(|(c, _)| *c == color)
+            .map_or("white", |(_, key)| key)
+            .into(),
+    }
+}
+
+pub fn parse_color(key: &str) -> Result<Color, String> {
+    let bad = || format!("unknown color `{}`", key);
+    if let Some(hex) = key.strip_prefix('#') {
+        let rgb = u32::from_str_radix(hex, 16).map_err(|_| bad())?;
+        return Ok(Color::Rgb {
+            r: (rgb >> 16) as u8,
+            g: (rgb >> 8) as u8,
+            b: rgb as u8,
+        });
+    }
+    if let Some(n) = key.strip_prefix("ansi:") {
+        return n.parse().map(Color::AnsiValue).map_err(|_| bad());
+    }
+    COLORS
+        .iter()
+        .find(|(_, k)| *k == key)
+        .map(|(c, _)| *c)
+        .ok_or_else(bad)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        physics::{PhysicsProfile, Preset},
+        settings::{Difficulty, Mode, Settings},
+        world::{tests::play, World},
+    };
+
+    #[test]
+    fn a_loaded_run_plays_on_the_same() {
+        let settings = Settings {
+            mode: Mode::Classic,
+            difficulty: Difficulty::Hard,
+            enemies: 4,
+            ..Settings::default()
+        };
+        let mut world = World::new(29, &settings, &PhysicsProfile::preset(Preset::Classic));
+        for _ in 0..600 {
+            play(&mut world);
+        }
+        let mut loaded =
+            World::from_save("save", &world.to_save()).unwrap_or_else(|e| panic!("{}", e));
+        assert_eq!(world.to_save(), loaded.to_save());
+        for _ in 0..1200 {
+            play(&mut world);
+            play(&mut loaded);
+        }
+        // Tags were made along the way, the run did not just stand there.
+        assert!(world.score() > 0);
+        assert_eq!(world.to_save(), loaded.to_save());
+    }
+}
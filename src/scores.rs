@@ -0,0 +1,81 @@
+use crate::{
+    error::Error,
+    settings::Mode,
+    store::{self, Choice},
+};
+
+const FILE: &str = "scores";
+const KEEP: usize = 5;
+
+#[derive(Clone, Copy)]
+pub struct Entry {
+    pub mode: Mode,
+    /// Tags, or frames for modes where lower is better.
+    pub value: u64,
+    pub seed: usize,
+}
+
+/// The best runs of each mode, best first.
+#[derive(Default)]
+pub struct HighScores(Vec<Entry>);
+
+impl HighScores {
+    pub fn load() -> Result<Self, Error> {
+        let mut scores = HighScores::default();
+        let Some(text) = store::read(FILE)? else {
+            return Ok(scores);
+        };
+        store::parse(FILE, &text, |key, value| {
+            let mode = Mode::parse(key)?;
+            let mut fields = value.split_whitespace().map(|f| f.parse::<u64>());
+            match (fields.next(), fields.next()) {
+                (Some(Ok(value)), Some(Ok(seed))) => {
+                    scores.0.push(Entry {
+                        mode,
+                        value,
+                        seed: seed as usize,
+                    });
+                    Ok(())
+                }
+                _ => Err("expected `<mode> = <score> <seed>`".into()),
+            }
+        })?;
+        Ok(scores)
+    }
+
+    fn save(&self) -> Result<(), Error> {
+        let text: String = self
+            .0
+            .iter()
+            .map(|e| format!("{} = {} {}\n", e.mode.key(), e.value, e.seed))
+            .collect();
+        store::write(FILE, &text)
+    }
+
+    pub fn best(&self, mode: Mode) -> impl Iterator<Item = &Entry> {
+        self.0.iter().filter(move |e| e.mode == mode)
+    }
+
+    /// Records a finished run and returns true when it is the new best.
+    pub fn submit(&mut self, entry: Entry) -> Result<bool, Error> {
+        let better = |a: &Entry, b: &Entry| {
+            if entry.mode.lower_is_better() {
+                a.value < b.value
+            } else {
+                a.value > b.value
+            }
+        };
+        let mut same: Vec<Entry> = self.best(entry.mode).copied().collect();
+        let is_best = same.first().is_none_or(|b| better(&entry, b));
+        let at = same
+            .iter()
+            .position(|e| better(&entry, e))
+            .unwrap_or(same.len());
+        same.insert(at, entry);
+        same.truncate(KEEP);
+        self.0.retain(|e| e.mode != entry.mode);
+        self.0.extend(same);
+        self.save()?;
+        Ok(is_best)
+    }
+}
@@ -0,0 +1,376 @@
+use crossterm::style::Color;
+
+use crate::{error::Error, physics::Preset, render::Palette, store, store::Choice, H, W};
+
+const FILE: &str = "config";
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    Classic,
+    TimeAttack,
+    Blitz,
+    /// Endless climb, the screen scrolls up and falling behind ends the run.
+    Climb,
+    /// Hunters chase the player, the run ends when one catches them.
+    Survival,
+    /// A blitz on a level of the day, the same for everyone.
+    Daily,
+}
+
+impl Choice for Mode {
+    const ALL: &'static [Self] = &[
+        Mode::Classic,
+        Mode::TimeAttack,
+        Mode::Blitz,
+        Mode::Climb,
+        Mode::Survival,
+        Mode::Daily,
+    ];
+
+    fn key(self) -> &'static str {
+        match self {
+            Mode::Classic => "classic",
+            Mode::TimeAttack => "time_attack",
+            Mode::Blitz => "blitz",
+            Mode::Climb => "climb",
+            Mode::Survival => "survival",
+            Mode::Daily => "daily",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Mode::Classic => "Classic",
+            Mode::TimeAttack => "Time attack",
+            Mode::Blitz => "Blitz",
+            Mode::Climb => "Climb",
+            Mode::Survival => "Survival",
+            Mode::Daily => "Daily",
+        }
+    }
+}
+
+impl Mode {
+    /// Tags needed to finish a time attack.
+    pub const TIME_ATTACK_TAGS: usize = 10;
+    /// Length of a blitz or daily challenge, in seconds.
+    pub const BLITZ_SECONDS: isize = 60;
+
+    /// Time attacks keep the fastest runs, everything else the highest score.
+    pub fn lower_is_better(self) -> bool {
+        self == Mode::TimeAttack
+    }
+}
+
+/// How large the level is. Anything larger than the screen scrolls.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Screen,
+    Wide,
+    Tall,
+    Large,
+}
+
+impl Choice for Level {
+    const ALL: &'static [Self] = &[Level::Screen, Level::Wide, Level::Tall, Level::Large];
+
+    fn key(self) -> &'static str {
+        match self {
+            Level::Screen => "screen",
+            Level::Wide => "wide",
+            Level::Tall => "tall",
+            Level::Large => "large",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Level::Screen => "Screen (80x24)",
+            Level::Wide => "Wide (240x24)",
+            Level::Tall => "Tall (80x96)",
+            Level::Large => "Large (200x64)",
+        }
+    }
+}
+
+impl Level {
+    /// Width and height, in cells.
+    pub fn size(self) -> (isize, isize) {
+        match self {
+            Level::Screen => (W, H),
+            Level::Wide => (W * 3, H),
+            Level::Tall => (W, H * 4),
+            Level::Large => (200, 64),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+}
+
+impl Choice for Difficulty {
+    const ALL: &'static [Self] = &[Difficulty::Easy, Difficulty::Normal, Difficulty::Hard];
+
+    fn key(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Normal => "normal",
+            Difficulty::Hard => "hard",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Normal => "Normal",
+            Difficulty::Hard => "Hard",
+        }
+    }
+}
+
+impl Difficulty {
+    /// Squared distance under which enemies notice the player and jump away.
+    pub fn reaction(self) -> isize {
+        match self {
+            Difficulty::Easy => 40,
+            Difficulty::Normal => 70,
+            Difficulty::Hard => 120,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Theme {
+    Classic,
+    Night,
+    Ember,
+    Mono,
+}
+
+impl Choice for Theme {
+    const ALL: &'static [Self] = &[Theme::Classic, Theme::Night, Theme::Ember, Theme::Mono];
+
+    fn key(self) -> &'static str {
+        match self {
+            Theme::Classic => "classic",
+            Theme::Night => "night",
+            Theme::Ember => "ember",
+            Theme::Mono => "mono",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Theme::Classic => "Classic",
+            Theme::Night => "Night",
+            Theme::Ember => "Ember",
+            Theme::Mono => "Mono",
+        }
+    }
+}
+
+impl Theme {
+    pub fn palette(self) -> Palette {
+        match self {
+            Theme::Classic => Palette {
+                wall: Color::DarkBlue,
+                solid: Color::DarkBlue,
+                hud: Color::DarkBlue,
+            },
+            Theme::Night => Palette {
+                wall: Color::DarkGrey,
+                solid: Color::DarkBlue,
+                hud: Color::DarkGrey,
+            },
+            Theme::Ember => Palette {
+                wall: Color::DarkRed,
+                solid: Color::DarkMagenta,
+                hud: Color::DarkRed,
+            },
+            Theme::Mono => Palette {
+                wall: Color::Grey,
+                solid: Color::DarkGrey,
+                hud: Color::DarkGrey,
+            },
+        }
+    }
+}
+
+/// Which keys move the player. Arrows always work.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Controls {
+    All,
+    Arrows,
+    Wasd,
+    Zqsd,
+}
+
+impl Choice for Controls {
+    const ALL: &'static [Self] = &[
+        Controls::All,
+        Controls::Arrows,
+        Controls::Wasd,
+        Controls::Zqsd,
+    ];
+
+    fn key(self) -> &'static str {
+        match self {
+            Controls::All => "all",
+            Controls::Arrows => "arrows",
+            Controls::Wasd => "wasd",
+            Controls::Zqsd => "zqsd",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Controls::All => "Arrows, WASD, ZQSD",
+            Controls::Arrows => "Arrows",
+            Controls::Wasd => "Arrows, WASD",
+            Controls::Zqsd => "Arrows, ZQSD",
+        }
+    }
+}
+
+/// Where sound effects go.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Audio {
+    Off,
+    /// The terminal bell, for tags and cleared waves only.
+    Bell,
+    /// Synthesized sound, written to `sound.wav` next to the settings.
+    #[cfg(feature = "synth")]
+    Wav,
+    /// Synthesized sound, thrown away. Runs the synthesizer for testing.
+    #[cfg(feature = "synth")]
+    Muted,
+}
+
+impl Choice for Audio {
+    #[cfg(not(feature = "synth"))]
+    const ALL: &'static [Self] = &[Audio::Off, Audio::Bell];
+    #[cfg(feature = "synth")]
+    const ALL: &'static [Self] = &[Audio::Off, Audio::Bell, Audio::Wav, Audio::Muted];
+
+    fn key(self) -> &'static str {
+        match self {
+            Audio::Off => "off",
+            Audio::Bell => "bell",
+            #[cfg(feature = "synth")]
+            Audio::Wav => "wav",
+            #[cfg(feature = "synth")]
+            Audio::Muted => "muted",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Audio::Off => "Off",
+            Audio::Bell => "Terminal bell",
+            #[cfg(feature = "synth")]
+            Audio::Wav => "WAV file",
+            #[cfg(feature = "synth")]
+            Audio::Muted => "Muted",
+        }
+    }
+}
+
+pub struct Settings {
+    pub mode: Mode,
+    pub level: Level,
+    pub difficulty: Difficulty,
+    pub enemies: usize,
+    pub theme: Theme,
+    pub controls: Controls,
+    pub physics: Preset,
+    pub pause_on_focus_loss: bool,
+    pub show_fps: bool,
+    pub sound: Audio,
+    /// Whether sprites two cells wide are drawn as such.
+    pub wide_sprites: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            mode: Mode::Classic,
+            level: Level::Screen,
+            difficulty: Difficulty::Normal,
+            enemies: 2,
+            theme: Theme::Classic,
+            controls: Controls::All,
+            physics: Preset::Classic,
+            pause_on_focus_loss: true,
+            show_fps: false,
+            sound: Audio::Off,
+            wide_sprites: false,
+        }
+    }
+}
+
+pub const MAX_ENEMIES: usize = 9;
+
+fn parse_bool(s: &str) -> Result<bool, String> {
+    match s {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        _ => Err(format!("expected true or false, got `{}`", s)),
+    }
+}
+
+impl Settings {
+    pub fn load() -> Result<Self, Error> {
+        let mut settings = Settings::default();
+        let Some(text) = store::read(FILE)? else {
+            return Ok(settings);
+        };
+        store::parse(FILE, &text, |key, value| {
+            match key {
+                "mode" => settings.mode = Mode::parse(value)?,
+                "level" => settings.level = Level::parse(value)?,
+                "difficulty" => settings.difficulty = Difficulty::parse(value)?,
+                "enemies" => {
+                    settings.enemies = value
+                        .parse()
+                        .ok()
+                        .filter(|n| (1..=MAX_ENEMIES).contains(n))
+                        .ok_or_else(|| format!("enemies must be between 1 and {}", MAX_ENEMIES))?
+                }
+                "theme" => settings.theme = Theme::parse(value)?,
+                "controls" => settings.controls = Controls::parse(value)?,
+                "physics" => settings.physics = Preset::parse(value)?,
+                "pause_on_focus_loss" => settings.pause_on_focus_loss = parse_bool(value)?,
+                "show_fps" => settings.show_fps = parse_bool(value)?,
+                // A config from a build with the synthesizer may ask for
+                // sound this one cannot make, it plays without instead.
+                "sound" => settings.sound = Audio::parse(value).unwrap_or(Audio::Off),
+                "wide_sprites" => settings.wide_sprites = parse_bool(value)?,
+                _ => return Err(format!("unknown setting `{}`", key)),
+            }
+            Ok(())
+        })?;
+        Ok(settings)
+    }
+
+    pub fn save(&self) -> Result<(), Error> {
+        let text = format!(
+            "mode = {}\nlevel = {}\ndifficulty = {}\nenemies = {}\ntheme = {}\ncontrols = {}\nphysics = {}\npause_on_focus_loss = {}\nshow_fps = {}\nsound = {}\nwide_sprites = {}\n",
+            self.mode.key(),
+            self.level.key(),
+            self.difficulty.key(),
+            self.enemies,
+            self.theme.key(),
+            self.controls.key(),
+            self.physics.key(),
+            self.pause_on_focus_loss,
+            self.show_fps,
+            self.sound.key(),
+            self.wide_sprites
+        );
+        store::write(FILE, &text)
+    }
+}
@@ -0,0 +1,153 @@
+use std::io::{stdout, Write};
+
+#[cfg(feature = "synth")]
+use crate::synth::{Null, Synth, WavFile};
+use crate::{enemy::Kind, error::Error, events::GameEvent, settings::Audio};
+
+/// A sound effect.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Sound {
+    Jump,
+    DoubleJump,
+    Dash,
+    Land,
+    Tag,
+    WaveClear,
+}
+
+impl Sound {
+    /// What an event sounds like. Only the player's own moves are heard, the
+    /// enemies would drown them out.
+    pub fn of(event: &GameEvent) -> Option<Sound> {
+        match *event {
+            GameEvent::Jump {
+                kind: Kind::Player, ..
+            } => Some(Sound::Jump),
+            GameEvent::DoubleJump {
+                kind: Kind::Player, ..
+            } => Some(Sound::DoubleJump),
+            GameEvent::Dash {
+                kind: Kind::Player, ..
+            } => Some(Sound::Dash),
+            GameEvent::Land {
+                kind: Kind::Player, ..
+            } => Some(Sound::Land),
+            GameEvent::Tag(_) => Some(Sound::Tag),
+            GameEvent::WaveClear => Some(Sound::WaveClear),
+            _ => None,
+        }
+    }
+
+    /// Whether the terminal bell rings for it. A bell on every jump would be
+    /// unbearable.
+    fn rings(self) -> bool {
+        matches!(self, Sound::Tag | Sound::WaveClear)
+    }
+}
+
+/// Where sounds are played.
+pub trait Output {
+    fn play(&mut self, sound: Sound);
+
+    /// Called once per tick of a run being played, after the sounds of that
+    /// tick.
+    fn tick(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Called when the output is dropped for another one, or on exit.
+    fn finish(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+struct Silent;
+
+impl Output for Silent {
+    fn play(&mut self, _: Sound) {}
+}
+
+/// Rings the terminal bell, at most once per tick.
+struct Bell {
+    ring: bool,
+}
+
+impl Output for Bell {
+    fn play(&mut self, sound: Sound) {
+        self.ring |= sound.rings();
+    }
+
+    fn tick(&mut self) -> Result<(), Error> {
+        if std::mem::take(&mut self.ring) {
+            let mut stdout = stdout();
+            stdout.write_all(b"\x07")?;
+            stdout.flush()?;
+        }
+        Ok(())
+    }
+}
+
+pub fn output(audio: Audio) -> Result<Box<dyn Output>, Error> {
+    Ok(match audio {
+        Audio::Off => silent(),
+        Audio::Bell => Box::new(Bell { ring: false }),
+        #[cfg(feature = "synth")]
+        Audio::Wav => match WavFile::create()? {
+            Some(file) => Box::new(Synth::new(file)),
+            None => Box::new(Synth::new(Null)),
+        },
+        #[cfg(feature = "synth")]
+        Audio::Muted => Box::new(Synth::new(Null)),
+    })
+}
+
+/// No sound at all, also what is left when an output fails.
+pub fn silent() -> Box<dyn Output> {
+    Box::new(Silent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Sound;
+    use crate::{
+        enemy::Kind,
+        events::{GameEvent, Tag},
+        world::Pos,
+    };
+
+    const POS: Pos = Pos { x: 3, y: 4 };
+
+    #[test]
+    fn the_player_moves_are_heard() {
+        let kind = Kind::Player;
+        assert!(Sound::of(&GameEvent::Jump { kind }) == Some(Sound::Jump));
+        assert!(Sound::of(&GameEvent::DoubleJump { kind }) == Some(Sound::DoubleJump));
+        assert!(Sound::of(&GameEvent::Dash { kind }) == Some(Sound::Dash));
+        assert!(Sound::of(&GameEvent::Land { kind, pos: POS }) == Some(Sound::Land));
+        assert!(Sound::of(&GameEvent::TakeOff { kind, pos: POS }).is_none());
+    }
+
+    #[test]
+    fn enemy_moves_are_not() {
+        let kind = Kind::Jumper;
+        assert!(Sound::of(&GameEvent::Jump { kind }).is_none());
+        assert!(Sound::of(&GameEvent::DoubleJump { kind }).is_none());
+        assert!(Sound::of(&GameEvent::Dash { kind }).is_none());
+        assert!(Sound::of(&GameEvent::Land { kind, pos: POS }).is_none());
+        assert!(Sound::of(&GameEvent::Hit { pos: POS }).is_none());
+        assert!(Sound::of(&GameEvent::Teleport { from: POS, to: POS }).is_none());
+    }
+
+    #[test]
+    fn tags_and_cleared_waves_are_heard() {
+        let tag = Tag {
+            kind: Kind::Runner,
+            pos: POS,
+            air: true,
+            double_jump: false,
+            dash: false,
+        };
+        assert!(Sound::of(&GameEvent::Tag(tag)) == Some(Sound::Tag));
+        assert!(Sound::of(&GameEvent::WaveClear) == Some(Sound::WaveClear));
+    }
+}
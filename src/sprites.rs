@@ -0,0 +1,178 @@
+use crate::{
+    enemy::Kind,
+    error::Error,
+    motion::squashed,
+    store::{self, Choice},
+    world::{Char, Dash},
+};
+
+const FILE: &str = "sprites";
+/// Ticks each animation frame shows for, unless the sprite file says
+/// otherwise.
+const TICKS: isize = 8;
+
+/// What a character is doing, each with its own animation.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Stance {
+    Idle,
+    Run,
+    Jump,
+    Fall,
+    Dash,
+    Land,
+    Stunned,
+}
+
+impl Choice for Stance {
+    const ALL: &'static [Self] = &[
+        Stance::Idle,
+        Stance::Run,
+        Stance::Jump,
+        Stance::Fall,
+        Stance::Dash,
+        Stance::Land,
+        Stance::Stunned,
+    ];
+
+    fn key(self) -> &'static str {
+        match self {
+            Stance::Idle => "idle",
+            Stance::Run => "run",
+            Stance::Jump => "jump",
+            Stance::Fall => "fall",
+            Stance::Dash => "dash",
+            Stance::Land => "land",
+            Stance::Stunned => "stunned",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        self.key()
+    }
+}
+
+impl Stance {
+    /// Characters only move sideways through the air or by dashing, so
+    /// running is drifting sideways on the way down.
+    pub fn of(char: &Char) -> Self {
+        if char.stun > 0 {
+            Stance::Stunned
+        } else if let Dash::Dashing(_) = char.dash {
+            Stance::Dash
+        } else if char.pose > 0 {
+            Stance::Land
+        } else if char.fly && char.vy < 0 {
+            Stance::Jump
+        } else if char.fly && char.pos.x != char.old_pos.x {
+            Stance::Run
+        } else if char.fly {
+            Stance::Fall
+        } else {
+            Stance::Idle
+        }
+    }
+}
+
+/// The frames of one animation, each one or two cells wide.
+struct Sprite {
+    kind: Kind,
+    stance: Stance,
+    /// -1 or 1 for only one side, `None` for both.
+    facing: Option<isize>,
+    frames: Vec<Vec<char>>,
+}
+
+/// The animations read from the sprite file. Anything it leaves out is
+/// drawn with the built-in glyphs.
+pub struct Sprites {
+    ticks: isize,
+    sprites: Vec<Sprite>,
+    /// Whether frames two cells wide show both cells, from the settings.
+    /// Otherwise only the character's own cell is drawn.
+    pub wide: bool,
+}
+
+impl Sprites {
+    /// Reads the sprite file, if there is one.
+    pub fn load(wide: bool) -> Result<Self, Error> {
+        let mut sprites = Sprites {
+            ticks: TICKS,
+            sprites: Vec::new(),
+            wide,
+        };
+        let Some(text) = store::read(FILE)? else {
+            return Ok(sprites);
+        };
+        store::parse(FILE, &text, |key, value| {
+            if key == "ticks" {
+                sprites.ticks = value
+                    .parse()
+                    .ok()
+                    .filter(|t| *t > 0)
+                    .ok_or("expected a number of ticks above 0")?;
+                return Ok(());
+            }
+            let mut parts = key.split('.');
+            let (Some(kind), Some(stance)) = (parts.next(), parts.next()) else {
+                return Err("expected `<kind>.<stance>[.left|.right] = <frames>`".into());
+            };
+            let kind = Kind::parse(kind).ok_or_else(|| format!("unknown kind `{}`", kind))?;
+            let stance = Stance::parse(stance)?;
+            let facing = match parts.next() {
+                None => None,
+                Some("left") => Some(-1),
+                Some("right") => Some(1),
+                Some(side) => return Err(format!("unknown side `{}`", side)),
+            };
+            let frames: Vec<Vec<char>> = value
+                .split_whitespace()
+                .map(|f| f.chars().collect())
+                .collect();
+            if frames.is_empty() || frames.iter().any(|f| f.len() > 2) {
+                return Err("expected frames of one or two characters".into());
+            }
+            sprites.sprites.push(Sprite {
+                kind,
+                stance,
+                facing,
+                frames,
+            });
+            Ok(())
+        })?;
+        Ok(sprites)
+    }
+
+    /// What to draw for a character at tick `frames`: the glyph of its own
+    /// cell and, for a wide frame, the other half with the side it goes on.
+    /// The character's own cell is on the side it faces.
+    pub fn frame(&self, char: &Char, frames: isize) -> (char, Option<(char, isize)>) {
+        let frame = self.glyphs(char, frames);
+        let (own, half) = match frame[..] {
+            [first, second] if char.right_power == 1 => (second, Some((first, -1))),
+            [first, second] => (first, Some((second, 1))),
+            _ => (frame[0], None),
+        };
+        (own, half.filter(|_| self.wide))
+    }
+
+    fn glyphs(&self, char: &Char, frames: isize) -> Vec<char> {
+        let stance = Stance::of(char);
+        let facing = char.right_power;
+        let found = [Some(facing), None].into_iter().find_map(|side| {
+            self.sprites
+                .iter()
+                .rev()
+                .find(|s| s.kind == char.kind && s.stance == stance && s.facing == side)
+        });
+        if let Some(sprite) = found {
+            let n = sprite.frames.len() as isize;
+            return sprite.frames[(frames / self.ticks % n) as usize].clone();
+        }
+        let glyph = char.kind.glyph(facing);
+        match stance {
+            Stance::Land => vec![squashed(glyph)],
+            Stance::Stunned => vec![['*', '+'][(frames / self.ticks % 2) as usize]],
+            _ => vec![glyph],
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::error::Error;
+
+/// Where settings, scores and everything else yjump remembers live:
+/// `$XDG_CONFIG_HOME/yjump`, `~/.config/yjump` or `%APPDATA%\yjump`.
+pub fn dir() -> Option<PathBuf> {
+    let env = |k| {
+        std::env::var_os(k)
+            .filter(|v| !v.is_empty())
+            .map(PathBuf::from)
+    };
+    env("XDG_CONFIG_HOME")
+        .or_else(|| env("HOME").map(|h| h.join(".config")))
+        .or_else(|| env("APPDATA"))
+        .map(|d| d.join("yjump"))
+}
+
+pub fn path(name: &str) -> Option<PathBuf> {
+    dir().map(|d| d.join(name))
+}
+
+/// Reads a stored file, `None` when it does not exist yet.
+pub fn read(name: &str) -> Result<Option<String>, Error> {
+    let Some(path) = path(name) else {
+        return Ok(None);
+    };
+    match fs::read_to_string(&path) {
+        Ok(text) => Ok(Some(text)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(error(&path, e)),
+    }
+}
+
+/// Writes a stored file through a temporary one, so that a crash never
+/// leaves it half written.
+pub fn write(name: &str, contents: &str) -> Result<(), Error> {
+    let Some(path) = path(name) else {
+        return Ok(());
+    };
+    let tmp = path.with_extension("tmp");
+    path.parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| fs::write(&tmp, contents))
+        .and_then(|_| fs::rename(&tmp, &path))
+        .map_err(|e| error(&path, e))
+}
+
+/// Creates a stored file to stream into, `None` when there is nowhere to
+/// store files.
+#[cfg(feature = "synth")]
+pub fn create(name: &str) -> Result<Option<fs::File>, Error> {
+    let Some(path) = path(name) else {
+        return Ok(None);
+    };
+    path.parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| fs::File::create(&path))
+        .map(Some)
+        .map_err(|e| error(&path, e))
+}
+
+/// Deletes a stored file, if there is one.
+pub fn remove(name: &str) -> Result<(), Error> {
+    let Some(path) = path(name) else {
+        return Ok(());
+    };
+    match fs::remove_file(&path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(error(&path, e)),
+        _ => Ok(()),
+    }
+}
+
+fn error(path: &Path, e: io::Error) -> Error {
+    Error::Config(format!("{}: {}", path.display(), e))
+}
+
+/// Calls `f` with every `key = value` line of a stored file, skipping
+/// blank lines and `#` comments. Errors are reported with the line number.
+pub fn parse(
+    name: &str,
+    text: &str,
+    mut f: impl FnMut(&str, &str) -> Result<(), String>,
+) -> Result<(), Error> {
+    for (n, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let result = match line.split_once('=') {
+            Some((key, value)) => f(key.trim(), value.trim()),
+            None => Err("expected `key = value`".into()),
+        };
+        if let Err(msg) = result {
+            let path = path(name).unwrap_or_else(|| name.into());
+            return Err(Error::Config(format!(
+                "{}:{}: {}",
+                path.display(),
+                n + 1,
+                msg
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// A setting with a fixed set of values, cycled through in menus and stored
+/// by key.
+pub trait Choice: Copy + PartialEq + 'static {
+    const ALL: &'static [Self];
+
+    fn key(self) -> &'static str;
+
+    fn label(self) -> &'static str;
+
+    fn parse(s: &str) -> Result<Self, String> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|c| c.key() == s)
+            .ok_or_else(|| {
+                let keys: Vec<_> = Self::ALL.iter().map(|c| c.key()).collect();
+                format!("unknown value `{}`, expected one of {}", s, keys.join(", "))
+            })
+    }
+
+    fn cycle(self, delta: isize) -> Self {
+        let n = Self::ALL.len() as isize;
+        let i = Self::ALL.iter().position(|c| *c == self).unwrap_or(0) as isize;
+        Self::ALL[(i + delta).rem_euclid(n) as usize]
+    }
+}
@@ -0,0 +1,264 @@
+//! A tiny synthesizer for the sound effects, built with the `synth`
+//! feature. It has no audio device to play on, so it writes what it plays
+//! to a WAV file instead, one game tick of samples at a time.
+
+use std::{
+    fs::File,
+    io::{BufWriter, Seek, SeekFrom, Write},
+};
+
+use crate::{
+    error::Error,
+    sound::{Output, Sound},
+    store, FPS,
+};
+
+const FILE: &str = "sound.wav";
+/// Samples per second, mono.
+const RATE: u32 = 24000;
+const PER_TICK: usize = RATE as usize / FPS as usize;
+/// Loudest a single voice gets, leaving room to mix a few.
+const VOLUME: f32 = 6000.0;
+
+/// Where synthesized samples go.
+pub trait Sink {
+    fn write(&mut self, samples: &[i16]) -> Result<(), Error>;
+
+    fn finish(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// Throws samples away.
+pub struct Null;
+
+impl Sink for Null {
+    fn write(&mut self, _: &[i16]) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// A 16-bit mono WAV file, streamed to and given its sizes once finished.
+pub struct WavFile {
+    file: BufWriter<File>,
+    samples: u32,
+}
+
+impl WavFile {
+    /// Starts `sound.wav` next to the settings, over the previous one.
+    pub fn create() -> Result<Option<Self>, Error> {
+        let Some(file) = store::create(FILE)? else {
+            return Ok(None);
+        };
+        let mut wav = WavFile {
+            file: BufWriter::new(file),
+            samples: 0,
+        };
+        wav.header()?;
+        Ok(Some(wav))
+    }
+
+    fn header(&mut self) -> Result<(), Error> {
+        let data = self.samples * 2;
+        let f = &mut self.file;
+        f.write_all(b"RIFF")?;
+        f.write_all(&(36 + data).to_le_bytes())?;
+        f.write_all(b"WAVEfmt ")?;
+        f.write_all(&16u32.to_le_bytes())?;
+        f.write_all(&1u16.to_le_bytes())?; // PCM
+        f.write_all(&1u16.to_le_bytes())?; // mono
+        f.write_all(&RATE.to_le_bytes())?;
+        f.write_all(&(RATE * 2).to_le_bytes())?;
+        f.write_all(&2u16.to_le_bytes())?;
+        f.write_all(&16u16.to_le_bytes())?;
+        f.write_all(b"data")?;
+        f.write_all(&data.to_le_bytes())?;
+        Ok(())
+    }
+}
+
+impl Sink for WavFile {
+    fn write(&mut self, samples: &[i16]) -> Result<(), Error> {
+        for s in samples {
+            self.file.write_all(&s.to_le_bytes())?;
+        }
+        self.samples += samples.len() as u32;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), Error> {
+        self.file.seek(SeekFrom::Start(0))?;
+        self.header()?;
+        self.file.flush()?;
+        Ok(())
+    }
+}
+
+enum Wave {
+    Square,
+    Noise,
+}
+
+/// How a sound is made: a wave sweeping from one frequency to another,
+/// fading out over its length.
+struct Patch {
+    wave: Wave,
+    from: f32,
+    to: f32,
+    seconds: f32,
+}
+
+fn patch(sound: Sound) -> Patch {
+    let (wave, from, to, seconds) = match sound {
+        Sound::Jump => (Wave::Square, 300.0, 600.0, 0.09),
+        Sound::DoubleJump => (Wave::Square, 450.0, 900.0, 0.09),
+        Sound::Dash => (Wave::Noise, 3000.0, 800.0, 0.12),
+        Sound::Land => (Wave::Square, 140.0, 70.0, 0.05),
+        Sound::Tag => (Wave::Square, 660.0, 1320.0, 0.12),
+        Sound::WaveClear => (Wave::Square, 400.0, 1600.0, 0.4),
+    };
+    Patch {
+        wave,
+        from,
+        to,
+        seconds,
+    }
+}
+
+/// A sound being played.
+struct Voice {
+    sound: Sound,
+    patch: Patch,
+    /// Samples played so far.
+    age: usize,
+    /// Where in the current period the wave is, from 0 to 1.
+    phase: f32,
+    /// The last noise level, held for a period.
+    level: f32,
+}
+
+impl Voice {
+    fn len(&self) -> usize {
+        (self.patch.seconds * RATE as f32) as usize
+    }
+
+    fn sample(&mut self, noise: &mut u32) -> f32 {
+        let t = self.age as f32 / self.len() as f32;
+        let freq = self.patch.from + (self.patch.to - self.patch.from) * t;
+        self.phase += freq / RATE as f32;
+        if self.phase >= 1.0 {
+            self.phase -= 1.0;
+            if let Wave::Noise = self.patch.wave {
+                *noise ^= *noise << 13;
+                *noise ^= *noise >> 17;
+                *noise ^= *noise << 5;
+                self.level = (*noise % 2000) as f32 / 1000.0 - 1.0;
+            }
+        }
+        self.age += 1;
+        let wave = match self.patch.wave {
+            Wave::Square if self.phase < 0.5 => 1.0,
+            Wave::Square => -1.0,
+            Wave::Noise => self.level,
+        };
+        wave * (1.0 - t)
+    }
+}
+
+/// Plays sounds by mixing them into samples for a sink, a tick at a time.
+pub struct Synth<S> {
+    sink: S,
+    voices: Vec<Voice>,
+    noise: u32,
+    buffer: Vec<i16>,
+}
+
+impl<S: Sink> Synth<S> {
+    pub fn new(sink: S) -> Self {
+        Self {
+            sink,
+            voices: Vec::new(),
+            noise: 0x2545_f491,
+            buffer: Vec::with_capacity(PER_TICK),
+        }
+    }
+}
+
+impl<S: Sink> Output for Synth<S> {
+    fn play(&mut self, sound: Sound) {
+        // The same sound twice in a tick would only be louder.
+        self.voices.retain(|v| v.age > 0 || v.sound != sound);
+        self.voices.push(Voice {
+            sound,
+            patch: patch(sound),
+            age: 0,
+            phase: 0.0,
+            level: 0.0,
+        });
+    }
+
+    fn tick(&mut self) -> Result<(), Error> {
+        self.buffer.clear();
+        for _ in 0..PER_TICK {
+            let mut mix = 0.0;
+            for voice in &mut self.voices {
+                if voice.age < voice.len() {
+                    mix += voice.sample(&mut self.noise);
+                }
+            }
+            let sample = (mix * VOLUME).clamp(i16::MIN as f32, i16::MAX as f32);
+            self.buffer.push(sample as i16);
+        }
+        self.voices.retain(|v| v.age < v.len());
+        self.sink.write(&self.buffer)
+    }
+
+    fn finish(&mut self) -> Result<(), Error> {
+        self.sink.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Sink, Synth, PER_TICK};
+    use crate::{
+        error::Error,
+        sound::{Output, Sound},
+    };
+
+    /// Keeps every sample written.
+    struct Capture(Vec<i16>);
+
+    impl Sink for Capture {
+        fn write(&mut self, samples: &[i16]) -> Result<(), Error> {
+            self.0.extend_from_slice(samples);
+            Ok(())
+        }
+    }
+
+    fn tick(synth: &mut Synth<Capture>) -> Vec<i16> {
+        synth.sink.0.clear();
+        assert!(synth.tick().is_ok());
+        assert_eq!(synth.sink.0.len(), PER_TICK);
+        synth.sink.0.clone()
+    }
+
+    #[test]
+    fn silent_without_sounds() {
+        let mut synth = Synth::new(Capture(Vec::new()));
+        assert!(tick(&mut synth).iter().all(|s| *s == 0));
+    }
+
+    #[test]
+    fn a_tag_sounds_then_ends() {
+        let mut synth = Synth::new(Capture(Vec::new()));
+        synth.play(Sound::Tag);
+        assert!(tick(&mut synth).iter().any(|s| *s != 0));
+        // A tag lasts 0.12s, a little over 7 ticks.
+        for _ in 0..7 {
+            tick(&mut synth);
+        }
+        assert!(synth.voices.is_empty());
+        assert!(tick(&mut synth).iter().all(|s| *s == 0));
+    }
+}
@@ -0,0 +1,137 @@
+use std::{
+    io::{stdout, Result},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use crossterm::{cursor, event, execute, style, terminal};
+
+use crate::{H, W};
+
+/// Whether a terminal of this size, in columns and rows, shows the whole
+/// board at one column per cell.
+pub fn shows_board((cols, rows): (u16, u16)) -> bool {
+    cols as isize >= W && rows as isize >= H
+}
+
+// Set while the terminal is in raw mode on the alternate screen, so that the
+// guard, the panic hook and the suspend handler never restore it twice.
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+pub fn enter() -> Result<()> {
+    execute!(
+        stdout(),
+        terminal::EnterAlternateScreen,
+        event::EnableFocusChange,
+        cursor::DisableBlinking,
+        cursor::Hide
+    )?;
+    terminal::enable_raw_mode()?;
+    ACTIVE.store(true, Ordering::SeqCst);
+    Ok(())
+}
+
+pub fn leave() -> Result<()> {
+    if !ACTIVE.swap(false, Ordering::SeqCst) {
+        return Ok(());
+    }
+    execute!(
+        stdout(),
+        cursor::Show,
+        style::ResetColor,
+        event::DisableFocusChange,
+        terminal::LeaveAlternateScreen
+    )?;
+    terminal::disable_raw_mode()
+}
+
+/// Restores the terminal when dropped, whatever way the game ends.
+pub struct Guard;
+
+impl Guard {
+    pub fn new() -> Result<Self> {
+        install_panic_hook();
+        enter()?;
+        Ok(Guard)
+    }
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        let _ = leave();
+    }
+}
+
+// The release profile aborts on panic, so the guard is never dropped there:
+// restore the terminal before the message is printed.
+fn install_panic_hook() {
+    let default = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let _ = leave();
+        default(info);
+    }));
+}
+
+pub enum Signal {
+    Quit,
+    Suspend,
+    Resume,
+}
+
+#[cfg(unix)]
+pub struct Signals {
+    quit: std::sync::Arc<AtomicBool>,
+    suspend: std::sync::Arc<AtomicBool>,
+    resume: std::sync::Arc<AtomicBool>,
+}
+
+#[cfg(unix)]
+impl Signals {
+    pub fn register() -> Result<Self> {
+        use signal_hook::{consts::*, flag};
+        let signals = Signals {
+            quit: Default::default(),
+            suspend: Default::default(),
+            resume: Default::default(),
+        };
+        flag::register(SIGTERM, signals.quit.clone())?;
+        flag::register(SIGHUP, signals.quit.clone())?;
+        flag::register(SIGTSTP, signals.suspend.clone())?;
+        flag::register(SIGCONT, signals.resume.clone())?;
+        Ok(signals)
+    }
+
+    pub fn take(&self) -> Option<Signal> {
+        if self.quit.swap(false, Ordering::SeqCst) {
+            Some(Signal::Quit)
+        } else if self.suspend.swap(false, Ordering::SeqCst) {
+            Some(Signal::Suspend)
+        } else if self.resume.swap(false, Ordering::SeqCst) {
+            Some(Signal::Resume)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(not(unix))]
+pub struct Signals;
+
+#[cfg(not(unix))]
+impl Signals {
+    pub fn register() -> Result<Self> {
+        Ok(Signals)
+    }
+
+    pub fn take(&self) -> Option<Signal> {
+        None
+    }
+}
+
+/// Hands the terminal back to the shell and stops the process, as Ctrl-Z
+/// would outside of raw mode. Returns once the process is continued.
+pub fn suspend() -> Result<()> {
+    leave()?;
+    #[cfg(unix)]
+    signal_hook::low_level::emulate_default_handler(signal_hook::consts::SIGTSTP)?;
+    enter()
+}
@@ -0,0 +1,127 @@
+use std::time::{Duration, Instant};
+
+use crossterm::style;
+
+use crate::{
+    render::{Palette, Screen},
+    FPS, H,
+};
+
+pub const TICK: Duration = Duration::from_micros(1_000_000 / FPS as u64);
+// Past this many ticks in one frame the game slows down instead of
+// fast-forwarding, so that a long stall does not teleport everyone.
+const MAX_TICKS: u32 = 5;
+const MAX_SKIPS: u32 = 3;
+
+/// Decouples simulation ticks, always `TICK` apart in game time, from
+/// rendered frames, which happen whenever the terminal keeps up.
+pub struct Clock {
+    last: Instant,
+    acc: Duration,
+    behind: bool,
+    skipped: u32,
+}
+
+impl Clock {
+    pub fn new() -> Self {
+        Self {
+            last: Instant::now(),
+            acc: Duration::ZERO,
+            behind: false,
+            skipped: 0,
+        }
+    }
+
+    /// Forgets the time spent stopped, after a suspend.
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Number of ticks to simulate since the last call.
+    pub fn ticks(&mut self) -> u32 {
+        let now = Instant::now();
+        self.acc += now - self.last;
+        self.last = now;
+        let due = (self.acc.as_micros() / TICK.as_micros()) as u32;
+        let ticks = due.min(MAX_TICKS);
+        self.acc = if due > MAX_TICKS {
+            Duration::ZERO
+        } else {
+            self.acc - TICK * ticks
+        };
+        self.behind = due > 1;
+        ticks
+    }
+
+    /// Whether to draw this frame. Frames are skipped while the simulation
+    /// is catching up, but never more than a few in a row.
+    pub fn render(&mut self) -> bool {
+        if self.behind && self.skipped < MAX_SKIPS {
+            self.skipped += 1;
+            false
+        } else {
+            self.skipped = 0;
+            true
+        }
+    }
+
+    /// Sleeps until the next tick is due.
+    pub fn wait(&self) {
+        if let Some(left) = TICK.checked_sub(self.acc + self.last.elapsed()) {
+            std::thread::sleep(left);
+        }
+    }
+}
+
+/// Frame rate and time spent per tick and per rendered frame, averaged
+/// over the last second.
+pub struct Stats {
+    since: Instant,
+    ticks: u32,
+    tick_time: Duration,
+    frames: u32,
+    frame_time: Duration,
+    shown: String,
+}
+
+impl Stats {
+    pub fn new() -> Self {
+        Self {
+            since: Instant::now(),
+            ticks: 0,
+            tick_time: Duration::ZERO,
+            frames: 0,
+            frame_time: Duration::ZERO,
+            shown: String::new(),
+        }
+    }
+
+    pub fn ticked(&mut self, ticks: u32, time: Duration) {
+        self.ticks += ticks;
+        self.tick_time += time;
+    }
+
+    pub fn rendered(&mut self, time: Duration) {
+        self.frames += 1;
+        self.frame_time += time;
+        let elapsed = self.since.elapsed();
+        if elapsed >= Duration::from_secs(1) {
+            let ms = |d: Duration, n: u32| d.as_secs_f64() * 1000.0 / n.max(1) as f64;
+            self.shown = format!(
+                " {:.0} fps  {} ticks  tick {:.2}ms  frame {:.2}ms ",
+                self.frames as f64 / elapsed.as_secs_f64(),
+                self.ticks,
+                ms(self.tick_time, self.ticks),
+                ms(self.frame_time, self.frames),
+            );
+            *self = Self {
+                shown: std::mem::take(&mut self.shown),
+                ..Self::new()
+            };
+        }
+    }
+
+    pub fn draw(&self, screen: &mut Screen, palette: &Palette) {
+        screen.print(1, H - 1, &self.shown, palette.hud, style::Color::White);
+    }
+}
@@ -0,0 +1,189 @@
+use std::path::Path;
+
+use crate::{
+    daily::{self, Share},
+    error::Error,
+    physics::{PhysicsProfile, Preset},
+    replay::{Playback, Replay},
+    settings::{Mode, MAX_ENEMIES},
+    store::Choice,
+    world::format_time,
+    FPS,
+};
+
+/// The longest run checked when the mode has no time limit: an hour.
+const MAX_FRAMES: isize = 60 * 60 * FPS as isize;
+
+fn fail<T>(msg: String) -> Result<T, Error> {
+    Err(Error::Verify(msg))
+}
+
+/// Plays a replay again headless and checks it against what it claims:
+/// the version it was recorded with, a seed, settings and physics the game
+/// could have picked, and how the run ended. Returns the physics preset.
+fn check(replay: &Replay) -> Result<Preset, Error> {
+    replay.playable().map_err(Error::Verify)?;
+    // xorshift never leaves zero, so no run ever had it.
+    if replay.seed == 0 {
+        return fail("seed 0 cannot be played".into());
+    }
+    if !(1..=MAX_ENEMIES).contains(&replay.enemies) {
+        return fail(format!("{} enemies cannot be picked", replay.enemies));
+    }
+    // Custom physics could be anything, only the presets compare.
+    let Some(preset) = Preset::ALL
+        .iter()
+        .filter(|p| **p != Preset::Custom)
+        .find(|p| PhysicsProfile::preset(**p) == replay.physics)
+    else {
+        return fail("the physics are not one of the presets".into());
+    };
+    // Playing a run again takes as long as its frames, so claims no run
+    // could make are turned down before that.
+    let longest = match replay.mode {
+        Mode::Blitz | Mode::Daily => Mode::BLITZ_SECONDS * FPS as isize,
+        _ => MAX_FRAMES,
+    };
+    if !(0..=longest).contains(&replay.frames) {
+        return fail(format!(
+            "the run claims {} frames, {} runs are checked up to {}",
+            replay.frames,
+            replay.mode.label(),
+            longest
+        ));
+    }
+    if replay.inputs.windows(2).any(|w| w[0].0 > w[1].0) {
+        return fail("inputs are out of order".into());
+    }
+    if replay
+        .inputs
+        .iter()
+        .any(|(tick, _)| !(0..=replay.frames).contains(tick))
+    {
+        return fail("inputs are outside of the run".into());
+    }
+
+    let mut playback = Playback::new(replay);
+    while playback.world().frames() < replay.frames && !playback.world().finished() {
+        playback.step();
+    }
+    let world = playback.world();
+    if world.frames() != replay.frames {
+        return fail(format!(
+            "the run ends after {} frames, not {}",
+            world.frames(),
+            replay.frames
+        ));
+    }
+    // Classic runs end whenever they are left, and so can the official daily
+    // attempt, the others by their rules.
+    if !matches!(replay.mode, Mode::Classic | Mode::Daily) && !world.finished() {
+        return fail(format!(
+            "the run is not over after {} frames",
+            replay.frames
+        ));
+    }
+    if world.score() != replay.score {
+        return fail(format!(
+            "the run scores {}, not {}",
+            world.score(),
+            replay.score
+        ));
+    }
+    Ok(*preset)
+}
+
+/// Checks a replay file on its own, for leaderboards.
+pub fn replay(path: &Path) -> Result<String, Error> {
+    let replay = Replay::read(path)?;
+    let preset = check(&replay)?;
+    Ok(format!(
+        "{} on seed {}, {} physics: {} points in {} frames ({}s), verified",
+        replay.mode.label(),
+        replay.seed,
+        preset.label(),
+        replay.score,
+        replay.frames,
+        format_time(replay.frames)
+    ))
+}
+
+/// Checks a shared daily result: that the replay is the one it points at,
+/// of that day's challenge, and that playing it again scores the same.
+pub fn daily(result: &str, replay: &Path) -> Result<String, Error> {
+    let Some(share) = Share::parse(result) else {
+        return fail(format!("`{}` is not a daily result", result));
+    };
+    let replay = Replay::read(replay)?;
+    if replay.hash() != share.hash {
+        return fail("the replay is not the one of this result".into());
+    }
+    let (settings, physics) = daily::rules();
+    if !replay.fits(&settings, &physics) || replay.seed != share.date.seed() {
+        return fail(format!(
+            "the replay is not of the daily challenge of {}",
+            share.date
+        ));
+    }
+    check(&replay)?;
+    let time = format_time(replay.frames);
+    if replay.score as u64 != share.score || time != share.time {
+        return fail(format!(
+            "the replay scores {} in {}s, not {} in {}s",
+            replay.score, time, share.score, share.time
+        ));
+    }
+    Ok(format!(
+        "{}: {} points in {}s, verified",
+        share.date, replay.score, time
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::check;
+    use crate::{
+        physics::{PhysicsProfile, Preset},
+        replay::Replay,
+        settings::{Mode, Settings},
+        world::{tests::play, World},
+    };
+
+    /// A blitz played to the end by the test script.
+    fn record(seed: usize) -> Replay {
+        let settings = Settings {
+            mode: Mode::Blitz,
+            ..Settings::default()
+        };
+        let mut world = World::new(seed, &settings, &PhysicsProfile::preset(Preset::Classic));
+        while !world.finished() {
+            play(&mut world);
+        }
+        world.replay().expect("the physics were not tuned")
+    }
+
+    #[test]
+    fn accepts_a_run_as_recorded() {
+        let replay = record(57);
+        assert!(replay.score > 0);
+        assert!(check(&replay).is_ok());
+    }
+
+    #[test]
+    fn rejects_another_score() {
+        let mut replay = record(57);
+        replay.score += 1;
+        assert!(check(&replay).is_err());
+        replay.score -= 2;
+        assert!(check(&replay).is_err());
+    }
+
+    #[test]
+    fn rejects_another_length() {
+        let mut replay = record(57);
+        replay.frames -= 1;
+        assert!(check(&replay).is_err());
+        replay.frames += 2;
+        assert!(check(&replay).is_err());
+    }
+}
@@ -0,0 +1,202 @@
+use crossterm::style::Color;
+
+use crate::{
+    render::{Palette, Pixel, Screen},
+    replay::{Playback, Replay},
+    sprites::Sprites,
+    world::{format_time, Action},
+    H, W,
+};
+
+// Ticks between two snapshots kept to step back from.
+const SNAPSHOT_EVERY: isize = 60;
+// Playback speeds, in quarters of the normal one.
+const SPEEDS: [usize; 5] = [1, 2, 4, 8, 16];
+// Ticks shown in the input overlay.
+const HISTORY: isize = 30;
+
+/// Watches a replay, with pausing, stepping both ways and slow motion, to
+/// look at what happened closely.
+pub struct Viewer {
+    replay: Replay,
+    playback: Playback,
+    /// The playback as it was every `SNAPSHOT_EVERY` ticks so far.
+    snapshots: Vec<Playback>,
+    speed: usize,
+    paused: bool,
+    /// Quarter ticks not played yet, at slow speeds.
+    owed: usize,
+}
+
+impl Viewer {
+    pub fn new(replay: Replay) -> Self {
+        let playback = Playback::new(&replay);
+        Self {
+            snapshots: vec![playback.clone()],
+            playback,
+            replay,
+            speed: 2,
+            paused: false,
+            owed: 0,
+        }
+    }
+
+    fn frames(&self) -> isize {
+        self.playback.world().frames()
+    }
+
+    fn at_end(&self) -> bool {
+        self.playback.world().finished() || self.frames() >= self.replay.frames
+    }
+
+    /// Plays one tick, unless the replay is over.
+    fn forward(&mut self) {
+        if self.at_end() {
+            return;
+        }
+        self.playback.step();
+        let frames = self.frames();
+        if frames % SNAPSHOT_EVERY == 0 && frames / SNAPSHOT_EVERY == self.snapshots.len() as isize
+        {
+            self.snapshots.push(self.playback.clone());
+        }
+    }
+
+    /// Goes to tick `frame`, from the last snapshot before it when going
+    /// back.
+    fn seek(&mut self, frame: isize) {
+        if frame < self.frames() {
+            let index = (frame / SNAPSHOT_EVERY) as usize;
+            self.playback = self.snapshots[index.min(self.snapshots.len() - 1)].clone();
+        }
+        while self.frames() < frame && !self.at_end() {
+            self.forward();
+        }
+    }
+
+    /// Advances by one game tick at the current speed.
+    pub fn tick(&mut self) {
+        if self.paused {
+            return;
+        }
+        self.owed += SPEEDS[self.speed];
+        while self.owed >= 4 {
+            self.owed -= 4;
+            self.forward();
+        }
+        if self.at_end() {
+            self.paused = true;
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.paused = !self.paused;
+    }
+
+    /// Pauses and moves one tick, `delta` is -1 or 1.
+    pub fn step(&mut self, delta: isize) {
+        self.paused = true;
+        self.seek((self.frames() + delta).max(0));
+    }
+
+    /// Changes the speed by one notch, `delta` is -1 or 1.
+    pub fn faster(&mut self, delta: isize) {
+        self.speed = (self.speed as isize + delta).clamp(0, SPEEDS.len() as isize - 1) as usize;
+    }
+
+    /// Plays until the next tag, or the end, and pauses there.
+    pub fn next_tag(&mut self) {
+        let tags = self.playback.world().tags();
+        while self.playback.world().tags() == tags && !self.at_end() {
+            self.forward();
+        }
+        self.paused = true;
+    }
+
+    pub fn draw(&self, screen: &mut Screen, palette: &Palette, sprites: &Sprites) {
+        self.playback.world().draw(screen, palette, sprites);
+
+        // Which keys were pressed after each of the last ticks, the current
+        // one on the right.
+        let frames = self.frames();
+        let (x0, y0) = (1, H - 7);
+        let status = format!(
+            " {}/{} x{}{} ",
+            frames,
+            self.replay.frames,
+            SPEEDS[self.speed] as f64 / 4.0,
+            if self.paused { " paused" } else { "" }
+        );
+        for x in 0..HISTORY + 2 {
+            screen.put(
+                x0 + x,
+                y0,
+                Pixel {
+                    back: palette.hud,
+                    front: palette.hud,
+                    char: ' ',
+                },
+            );
+        }
+        screen.print(x0, y0, &status, palette.hud, Color::White);
+        let first = frames - HISTORY + 1;
+        let inputs = &self.replay.inputs;
+        let recent = &inputs[inputs.partition_point(|(t, _)| *t < first)..];
+        for (row, (action, label)) in [
+            (Action::Left, '<'),
+            (Action::Right, '>'),
+            (Action::Jump, '^'),
+            (Action::Down, 'v'),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let y = y0 + 1 + row as isize;
+            for (x, char) in [(x0, label), (x0 + 1, ' ')] {
+                screen.put(
+                    x,
+                    y,
+                    Pixel {
+                        back: palette.hud,
+                        front: Color::White,
+                        char,
+                    },
+                );
+            }
+            for i in 0..HISTORY {
+                let tick = first + i;
+                let pressed = recent
+                    .iter()
+                    .take_while(|(t, _)| *t <= frames)
+                    .any(|&(t, a)| t == tick && a == action);
+                let back = match (pressed, tick == frames) {
+                    (true, true) => Color::White,
+                    (true, false) => Color::Yellow,
+                    (false, true) => Color::DarkGrey,
+                    (false, false) => Color::Black,
+                };
+                screen.put(
+                    x0 + 2 + i,
+                    y,
+                    Pixel {
+                        back,
+                        front: back,
+                        char: ' ',
+                    },
+                );
+            }
+        }
+
+        let help = format!(
+            " {}s  space pause  left/right step  up/down speed  n next tag  q quit ",
+            format_time(frames)
+        );
+        screen.print(
+            W / 2 - help.chars().count() as isize / 2,
+            H - 1,
+            &help,
+            palette.hud,
+            Color::White,
+        );
+    }
+}
@@ -0,0 +1,1726 @@
+use std::collections::VecDeque;
+
+use crossterm::style;
+
+use crate::{
+    camera::Camera,
+    daily::Date,
+    enemy::{think, Kind, Senses, MIMIC_DELAY},
+    error::Error,
+    events::{GameEvent, Tag},
+    motion::{
+        draw_afterimages, draw_pose, draw_speed_lines, leave_afterimages, update_afterimages,
+        Afterimage, POSE_TICKS,
+    },
+    particles::{
+        draw_particles, draw_popups, emit, spawn_popup, update_particles, update_popups, Effect,
+        Particle, Popup,
+    },
+    physics::{PhysicsProfile, Preset},
+    render::{Palette, Pixel, Screen},
+    replay::{Replay, VERSION},
+    save::Fields,
+    settings::{Difficulty, Level, Mode, Settings},
+    sprites::Sprites,
+    store::{self, Choice},
+    FPS, H, W,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Pos {
+    pub x: isize,
+    pub y: isize,
+}
+#[repr(u8)]
+#[derive(Clone, Copy)]
+pub enum Cell {
+    Air,
+    Solid,
+    Wall,
+}
+
+/// The level, which may be larger than the screen.
+#[derive(Clone)]
+pub struct Map {
+    pub w: isize,
+    pub h: isize,
+    /// Row of the first cell, which goes negative while climbing.
+    pub top: isize,
+    cells: Vec<Cell>,
+}
+
+impl Map {
+    /// The cell at `x, y`, walls all around outside.
+    pub fn get(&self, x: isize, y: isize) -> Cell {
+        let row = y - self.top;
+        if (0..self.w).contains(&x) && (0..self.h).contains(&row) {
+            self.cells[(x + row * self.w) as usize]
+        } else {
+            Cell::Wall
+        }
+    }
+
+    /// Adds a row of platforms on top.
+    fn grow(&mut self, rand: &mut Rand) {
+        let w = self.w;
+        let mut row = vec![Cell::Air; w as usize];
+        row[0] = Cell::Wall;
+        row[w as usize - 1] = Cell::Wall;
+        for x in 1..w - 1 {
+            if rand.next() % 60 < 1 {
+                let len = 2 + (rand.next() % 2) as isize;
+                for u in (x - len).max(1)..(x + len).min(w - 1) {
+                    row[u as usize] = Cell::Solid;
+                }
+            }
+        }
+        self.cells.splice(0..0, row);
+        self.top -= 1;
+        self.h += 1;
+    }
+
+    /// A row as a line of the save file.
+    fn row_to_save(&self, row: isize) -> String {
+        let start = (row * self.w) as usize;
+        self.cells[start..start + self.w as usize]
+            .iter()
+            .map(|c| match c {
+                Cell::Air => '.',
+                Cell::Solid => '=',
+                Cell::Wall => '#',
+            })
+            .collect()
+    }
+
+    fn row_from_save(&mut self, line: &str) -> Result<(), String> {
+        if line.chars().count() != self.w as usize {
+            return Err(format!("rows must be {} cells wide", self.w));
+        }
+        for c in line.chars() {
+            self.cells.push(match c {
+                '.' => Cell::Air,
+                '=' => Cell::Solid,
+                '#' => Cell::Wall,
+                c => return Err(format!("unknown cell `{}`", c)),
+            });
+        }
+        Ok(())
+    }
+
+    /// Forgets the rows from `y` down.
+    fn cut(&mut self, y: isize) {
+        let h = (y - self.top).clamp(0, self.h);
+        self.cells.truncate((h * self.w) as usize);
+        self.h = h;
+    }
+}
+
+/// A level with walls all around, `margin` columns thick on the sides.
+fn gen_map(w: isize, h: isize, margin: isize, rand: &mut Rand) -> Map {
+    let mut map = vec![Cell::Air; (w * h) as usize];
+    let (left, right) = (margin, w - 1 - margin);
+    for x in 0..w {
+        for y in 0..h {
+            let index = (x + y * w) as usize;
+            let border = x <= left || x >= right || y == 0 || y == h - 1;
+            if border {
+                map[index] = Cell::Wall;
+            } else {
+                let r = rand.next() % 60;
+
+                if r < 1 {
+                    map[index] = Cell::Solid;
+                    let len = 2 + (rand.next() % 2) as isize;
+                    for u in -len..len {
+                        if x + u > left && x + u < right {
+                            let index = (x + u + y * w) as usize;
+                            map[index] = Cell::Solid;
+                        }
+                    }
+                }
+            }
+        }
+    }
+    Map {
+        w,
+        h,
+        top: 0,
+        cells: map,
+    }
+}
+
+#[derive(Clone)]
+pub struct Rand(pub usize);
+impl Rand {
+    /// Seeds from the clock, for everything that does not need to be replayed.
+    pub fn from_time() -> Self {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos() as usize)
+            .unwrap_or(5);
+        // xorshift never leaves zero
+        Rand(nanos | 1)
+    }
+
+    pub fn next(&mut self) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        self.0
+    }
+}
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Dash {
+    Ready,
+    Dashing(usize),
+    Loading(usize),
+}
+// How the screen scrolls while climbing: still for a few seconds, then
+// speeding up by CLIMB_SPEED every CLIMB_RAMP seconds, in 1/SUB of a cell
+// per tick.
+const CLIMB_DELAY: isize = 3;
+const CLIMB_SPEED: isize = SUB / 32;
+const CLIMB_RAMP: isize = 20;
+const CLIMB_MAX: isize = SUB / 6;
+
+const MAX_WAVE_ENEMIES: usize = 12;
+
+// Bonus points for tagging in style, and for chaining tags less than
+// COMBO_WINDOW ticks apart, which multiplies the points of the tag.
+const AIR_BONUS: usize = 1;
+const DOUBLE_JUMP_BONUS: usize = 1;
+const DASH_BONUS: usize = 2;
+const COMBO_WINDOW: isize = 2 * FPS as isize;
+pub const MAX_MULTIPLIER: usize = 5;
+
+// Largest size of the minimap, in cells.
+const MINIMAP_W: isize = 24;
+const MINIMAP_H: isize = 8;
+
+// Positions and velocities are fixed point, in 1/SUB of a cell, so that
+// arcs are smooth while staying deterministic.
+pub const SUB: isize = 256;
+
+#[derive(Clone)]
+pub struct Char {
+    pub pos: Pos,
+    pub old_pos: Pos,
+    // Sub-cell position, `pos` is the cell it falls in.
+    pub fx: isize,
+    pub fy: isize,
+    pub vx: isize,
+    pub vy: isize,
+    pub right_power: isize,
+    pub last_power_frame: isize,
+    pub jump: i32,
+    pub double_jump_ready: bool,
+    pub fly: bool,
+    pub down: bool,
+    pub kind: Kind,
+    pub dash: Dash,
+    /// Ticks left before a stomped character can jump again.
+    pub stun: usize,
+    /// Ticks before an enemy can use its ability again.
+    pub cooldown: usize,
+    /// Ticks left squashed after landing when positive, stretched after
+    /// jumping when negative. Only for show, and not saved.
+    pub pose: isize,
+}
+
+impl Default for Char {
+    fn default() -> Self {
+        let mut char = Self {
+            pos: Pos { x: 0, y: 0 },
+            old_pos: Pos { x: 0, y: 0 },
+            fx: 0,
+            fy: 0,
+            vx: 0,
+            vy: 0,
+            right_power: 0_isize,
+            last_power_frame: 0,
+            jump: 0,
+            double_jump_ready: true,
+            fly: false,
+            down: false,
+            kind: Kind::Player,
+            dash: Dash::Ready,
+            stun: 0,
+            cooldown: 0,
+            pose: 0,
+        };
+        char.place(Pos { x: W / 2, y: H - 2 });
+        char
+    }
+}
+
+impl Char {
+    /// As a line of the save file.
+    fn to_save(&self) -> String {
+        let dash = match self.dash {
+            Dash::Ready => "ready".into(),
+            Dash::Dashing(n) => format!("dashing:{}", n),
+            Dash::Loading(n) => format!("loading:{}", n),
+        };
+        format!(
+            "{} {} {} {} {} {} {} {} {} {} {} {} {} {} {} {} {} {}",
+            self.kind.key(),
+            self.pos.x,
+            self.pos.y,
+            self.old_pos.x,
+            self.old_pos.y,
+            self.fx,
+            self.fy,
+            self.vx,
+            self.vy,
+            self.right_power,
+            self.last_power_frame,
+            self.jump,
+            self.double_jump_ready,
+            self.fly,
+            self.down,
+            dash,
+            self.stun,
+            self.cooldown
+        )
+    }
+
+    fn from_save(mut f: Fields) -> Result<Self, String> {
+        let kind = f.word()?;
+        let mut char = Char {
+            kind: Kind::parse(kind).ok_or_else(|| format!("unknown kind `{}`", kind))?,
+            pos: Pos {
+                x: f.next()?,
+                y: f.next()?,
+            },
+            old_pos: Pos {
+                x: f.next()?,
+                y: f.next()?,
+            },
+            fx: f.next()?,
+            fy: f.next()?,
+            vx: f.next()?,
+            vy: f.next()?,
+            right_power: f.next()?,
+            last_power_frame: f.next()?,
+            jump: f.next()?,
+            double_jump_ready: f.next()?,
+            fly: f.next()?,
+            down: f.next()?,
+            ..Char::default()
+        };
+        let dash = f.word()?;
+        char.dash = match dash.split_once(':') {
+            None if dash == "ready" => Dash::Ready,
+            Some(("dashing", n)) => Dash::Dashing(n.parse().map_err(|_| "bad dash")?),
+            Some(("loading", n)) => Dash::Loading(n.parse().map_err(|_| "bad dash")?),
+            _ => return Err(format!("unknown dash `{}`", dash)),
+        };
+        char.stun = f.next()?;
+        char.cooldown = f.next()?;
+        Ok(char)
+    }
+
+    /// Puts the character in the middle of a cell.
+    pub fn place(&mut self, pos: Pos) {
+        self.pos = pos;
+        self.old_pos = pos;
+        self.fx = pos.x * SUB + SUB / 2;
+        self.fy = pos.y * SUB + SUB / 2;
+    }
+
+    fn sync(&mut self) {
+        self.pos = Pos {
+            x: self.fx.div_euclid(SUB),
+            y: self.fy.div_euclid(SUB),
+        };
+    }
+
+    /// The side, -1 or 1, of a wall or platform the character is against,
+    /// preferring the one it faces.
+    fn wall_side(&self, map: &Map) -> Option<isize> {
+        let here = map.get(self.pos.x, self.pos.y);
+        let facing = if self.right_power == 0 {
+            1
+        } else {
+            self.right_power
+        };
+        [facing, -facing]
+            .into_iter()
+            .find(|s| blocks_side(here, map.get(self.pos.x + s, self.pos.y)))
+    }
+
+    /// The cells crossed during the last tick, from `old_pos` to `pos`.
+    pub fn trail(&self) -> impl Iterator<Item = Pos> {
+        let to = self.pos;
+        let mut at = Some(self.old_pos);
+        std::iter::from_fn(move || {
+            let p = at?;
+            at = (p != to).then(|| Pos {
+                x: p.x + (to.x - p.x).signum(),
+                y: p.y + (to.y - p.y).signum(),
+            });
+            Some(p)
+        })
+    }
+
+    /// Whether the paths of both characters met during the last tick, so
+    /// that fast characters cannot pass through each other.
+    fn crossed(&self, other: &Char) -> bool {
+        self.trail().any(|p| other.trail().any(|q| p == q))
+    }
+
+    /// Whether the character fell onto `other` from above.
+    fn stomps(&self, other: &Char) -> bool {
+        self.pos.y > self.old_pos.y && self.old_pos.y < other.old_pos.y
+    }
+
+    fn jump_speed(&self, physics: &PhysicsProfile) -> isize {
+        if self.right_power == 0 {
+            -physics.jump
+        } else {
+            -physics.run_jump
+        }
+    }
+}
+
+// Platforms can be jumped through from below, but not walked into from the
+// side, except by a character already inside one on its way up.
+fn blocks_fall(cell: Cell) -> bool {
+    matches!(cell, Cell::Solid | Cell::Wall)
+}
+
+fn blocks_rise(cell: Cell) -> bool {
+    matches!(cell, Cell::Wall)
+}
+
+fn blocks_side(from: Cell, cell: Cell) -> bool {
+    match cell {
+        Cell::Wall => true,
+        Cell::Solid => !matches!(from, Cell::Solid),
+        Cell::Air => false,
+    }
+}
+
+/// Sends a character flying with the given speed.
+fn launch(char: &mut Char, vx: isize, vy: isize) {
+    char.vx = vx;
+    char.vy = vy;
+    char.fly = true;
+}
+
+/// What happens when the paths of two characters meet: stomping someone
+/// stuns them, dashing into someone knocks them back, and otherwise both
+/// bump away from each other.
+fn collide(a: &mut Char, b: &mut Char, physics: &PhysicsProfile, events: &mut Vec<GameEvent>) {
+    if a.stomps(b) {
+        stomp(a, b, physics, events);
+        return;
+    }
+    if b.stomps(a) {
+        stomp(b, a, physics, events);
+        return;
+    }
+    let a_dashing = matches!(a.dash, Dash::Dashing(_));
+    let b_dashing = matches!(b.dash, Dash::Dashing(_));
+    if a_dashing || b_dashing {
+        if a_dashing {
+            launch(b, a.right_power * physics.knockback, -physics.knockback);
+            events.push(GameEvent::Hit { pos: b.pos });
+        }
+        if b_dashing {
+            launch(a, b.right_power * physics.knockback, -physics.knockback);
+            events.push(GameEvent::Hit { pos: a.pos });
+        }
+        return;
+    }
+    bounce(a, b, physics);
+}
+
+/// Stuns the character below, and bounces the one on top back up with its
+/// double jump.
+fn stomp(top: &mut Char, bottom: &mut Char, physics: &PhysicsProfile, events: &mut Vec<GameEvent>) {
+    bottom.stun = physics.stun;
+    if let Dash::Dashing(_) = bottom.dash {
+        bottom.dash = Dash::Loading(physics.dash_cooldown);
+    }
+    top.vy = -physics.jump;
+    top.double_jump_ready = true;
+    events.push(GameEvent::Hit { pos: bottom.pos });
+}
+
+/// Pushes two characters apart, the way they came from.
+fn bounce(a: &mut Char, b: &mut Char, physics: &PhysicsProfile) {
+    let side = [
+        a.pos.x - b.pos.x,
+        a.old_pos.x - b.old_pos.x,
+        b.right_power - a.right_power,
+        1,
+    ]
+    .into_iter()
+    .map(isize::signum)
+    .find(|s| *s != 0)
+    .unwrap_or(1);
+    launch(a, side * physics.bounce, -physics.bounce);
+    launch(b, -side * physics.bounce, -physics.bounce);
+}
+
+/// Moves by `vx`, checking every column crossed on the way.
+fn move_x(char: &mut Char, map: &Map) {
+    let target = char.fx + char.vx;
+    let to = target.div_euclid(SUB);
+    let step = (to - char.pos.x).signum();
+    let mut x = char.pos.x;
+    while x != to {
+        if blocks_side(map.get(x, char.pos.y), map.get(x + step, char.pos.y)) {
+            char.fx = x * SUB + SUB / 2;
+            char.vx = 0;
+            char.sync();
+            return;
+        }
+        x += step;
+    }
+    char.fx = target;
+    char.sync();
+}
+
+/// Moves by `vy`, checking every row crossed on the way. Returns true when
+/// the character lands.
+fn move_y(char: &mut Char, map: &Map) -> bool {
+    let target = char.fy + char.vy;
+    let to = target.div_euclid(SUB);
+    let step = (to - char.pos.y).signum();
+    let mut y = char.pos.y;
+    while y != to {
+        let next = map.get(char.pos.x, y + step);
+        if (step > 0 && blocks_fall(next)) || (step < 0 && blocks_rise(next)) {
+            char.fy = y * SUB + SUB / 2;
+            char.vy = 0;
+            char.sync();
+            return step > 0;
+        }
+        y += step;
+    }
+    char.fy = target;
+    char.sync();
+    false
+}
+
+fn update_char(char: &mut Char, map: &Map, physics: &PhysicsProfile, events: &mut Vec<GameEvent>) {
+    let kind = char.kind;
+    if char.dash == Dash::Dashing(physics.dash_length) {
+        events.push(GameEvent::Dash { kind });
+    }
+    char.dash = match char.dash {
+        Dash::Loading(x) if x > 0 => Dash::Loading(x - 1),
+        Dash::Dashing(x) if x > 0 => Dash::Dashing(x - 1),
+        Dash::Dashing(_) => {
+            // The dash speed only lasts as long as the dash. On the ground
+            // the character stops, anywhere else it carries on at running
+            // speed like after a jump.
+            char.vx = if blocks_fall(map.get(char.pos.x, char.pos.y + 1)) {
+                0
+            } else {
+                char.right_power * physics.run
+            };
+            Dash::Loading(physics.dash_cooldown)
+        }
+        _ => Dash::Ready,
+    };
+    char.old_pos = char.pos;
+    char.pose -= char.pose.signum();
+    if char.stun > 0 {
+        char.stun -= 1;
+        char.jump = 0;
+        char.down = false;
+    }
+    let dashing = matches!(char.dash, Dash::Dashing(_));
+
+    if dashing {
+        char.vx = char.right_power * physics.dash_speed;
+        char.vy = 0;
+        move_x(char, map);
+        return;
+    }
+
+    let fly0 = char.fly;
+    if !char.fly && char.jump > 0 {
+        char.double_jump_ready = true;
+        char.vy = char.jump_speed(physics);
+        char.vx = char.right_power * physics.run;
+        char.fly = true;
+        char.jump = 0;
+        char.pose = -POSE_TICKS;
+        events.push(GameEvent::Jump { kind });
+    }
+
+    if !char.fly && !blocks_fall(map.get(char.pos.x, char.pos.y + 1)) {
+        char.fly = true;
+    }
+
+    if char.fly && !fly0 {
+        events.push(GameEvent::TakeOff {
+            kind,
+            pos: char.pos,
+        });
+    }
+
+    if char.fly {
+        // Jumping off a wall works like jumping off the ground, it gives the
+        // double jump back.
+        if char.jump > 0 {
+            if let Some(side) = char.wall_side(map) {
+                char.jump = 0;
+                char.double_jump_ready = true;
+                char.right_power = -side;
+                char.vy = -physics.wall_jump;
+                char.vx = -side * physics.run;
+                char.pose = -POSE_TICKS;
+                events.push(GameEvent::Jump { kind });
+            } else if char.double_jump_ready && char.kind.double_jumps() {
+                char.jump = 0;
+                char.double_jump_ready = false;
+                char.vy = char.jump_speed(physics);
+                char.vx = char.right_power * physics.run;
+                char.pose = -POSE_TICKS;
+                events.push(GameEvent::DoubleJump { kind });
+            }
+        }
+
+        if char.down {
+            char.vx = 0;
+            char.vy = char.vy.max(0);
+        }
+
+        move_x(char, map);
+        let floored = move_y(char, map);
+
+        if floored {
+            char.fly = false;
+            char.pose = POSE_TICKS;
+            events.push(GameEvent::Land {
+                kind,
+                pos: char.pos,
+            });
+            char.vy = 0;
+            char.vx = 0
+        } else {
+            char.vy = (char.vy + physics.gravity).min(physics.max_fall);
+            let sliding = char.right_power != 0 && char.wall_side(map) == Some(char.right_power);
+            if sliding {
+                char.vy = char.vy.min(physics.wall_slide);
+            }
+        }
+    }
+    char.down = false;
+    char.jump = (char.jump - 1).max(0);
+}
+
+/// Frames as seconds with hundredths, `12.34`.
+pub fn format_time(frames: isize) -> String {
+    let hundredths = frames.max(0) * 100 / FPS as isize;
+    format!("{}.{:02}", hundredths / 100, hundredths % 100)
+}
+
+/// Where the score came from.
+#[derive(Clone, Default)]
+pub struct Breakdown {
+    pub enemies: usize,
+    pub air: usize,
+    pub double_jump: usize,
+    pub dash: usize,
+    pub combo: usize,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Left,
+    Right,
+    Jump,
+    Down,
+}
+
+impl Action {
+    /// Name in replay files.
+    pub fn key(self) -> &'static str {
+        match self {
+            Action::Left => "left",
+            Action::Right => "right",
+            Action::Jump => "jump",
+            Action::Down => "down",
+        }
+    }
+
+    pub fn parse(key: &str) -> Option<Self> {
+        [Action::Left, Action::Right, Action::Jump, Action::Down]
+            .into_iter()
+            .find(|a| a.key() == key)
+    }
+}
+
+/// Applies an input to a character, the player's or a mimic's.
+pub fn press(char: &mut Char, action: Action, frames: isize, physics: &PhysicsProfile) {
+    if char.stun > 0 {
+        return;
+    }
+    match action {
+        Action::Right | Action::Left => {
+            let power = if let Action::Right = action { 1 } else { -1 };
+            if char.dash == Dash::Ready
+                && char.right_power == power
+                && (frames - char.last_power_frame) < physics.double_tap
+            {
+                char.dash = Dash::Dashing(physics.dash_length)
+            }
+            if let Dash::Dashing(_) = char.dash {
+            } else {
+                char.right_power = power;
+                char.last_power_frame = frames;
+            }
+        }
+        Action::Jump => {
+            char.jump = physics.jump_buffer;
+        }
+        Action::Down => {
+            char.down = true;
+            char.right_power = 0;
+        }
+    }
+}
+
+/// Everything that is simulated, as opposed to menus and the terminal.
+#[derive(Clone)]
+pub struct World {
+    pub seed: usize,
+    pub mode: Mode,
+    /// Whether this is the day's official daily attempt, which counts
+    /// whether it is finished or left.
+    pub official: bool,
+    level: Level,
+    difficulty: Difficulty,
+    physics: PhysicsProfile,
+    rand: Rand,
+    /// Randomness for particles and other effects, apart from the
+    /// simulation so that they never change how a run plays.
+    fx: Rand,
+    map: Map,
+    camera: Camera,
+    player: Char,
+    enemies: Vec<Char>,
+    /// Number of enemies in the first wave, each wave brings one more.
+    first_wave: usize,
+    wave: usize,
+    /// Thickness of the side walls, which close in wave after wave.
+    margin: isize,
+    particles: Vec<Particle>,
+    /// Where dashing characters were lately, not saved.
+    afterimages: Vec<Afterimage>,
+    frames: isize,
+    score: usize,
+    breakdown: Breakdown,
+    /// Number of tags chained so far, and when the last one happened.
+    combo: usize,
+    last_tag: Option<isize>,
+    popups: Vec<Popup>,
+    tags: usize,
+    /// Whether a hunter caught the player, which ends a survival run.
+    caught: bool,
+    /// The player's recent inputs, for mimics to replay.
+    inputs: VecDeque<(isize, Action)>,
+    /// Every input of the run with the tick it came after, to replay it.
+    recorded: Vec<(isize, Action)>,
+    /// Whether the physics changed during the run, which a replay cannot
+    /// reproduce.
+    tuned: bool,
+    /// What happened during the last tick.
+    events: Vec<GameEvent>,
+    switching: isize,
+}
+
+impl World {
+    pub fn new(seed: usize, settings: &Settings, physics: &PhysicsProfile) -> Self {
+        let mut rand = Rand(seed);
+        let climb = settings.mode == Mode::Climb;
+        let (w, h) = settings.level.size();
+        // Climbing starts one screen high and grows from there, everything
+        // else is set up by the first wave.
+        let h = if climb { H } else { h };
+        let map = if climb {
+            let mut map = Map {
+                w,
+                h: 1,
+                top: h - 1,
+                cells: vec![Cell::Wall; w as usize],
+            };
+            for _ in 1..h {
+                map.grow(&mut rand);
+            }
+            map
+        } else {
+            gen_map(w, h, 0, &mut rand)
+        };
+        let mut player = Char::default();
+        player.place(Pos { x: w / 2, y: h - 2 });
+        let mut world = Self {
+            seed,
+            mode: settings.mode,
+            official: false,
+            level: settings.level,
+            difficulty: settings.difficulty,
+            physics: physics.clone(),
+            rand,
+            fx: Rand(seed.rotate_left(17) | 1),
+            map,
+            camera: Camera::new(w, h, player.pos),
+            player,
+            enemies: Vec::new(),
+            first_wave: settings.enemies,
+            wave: 0,
+            margin: 0,
+            particles: Vec::new(),
+            afterimages: Vec::new(),
+            frames: 0,
+            score: 0,
+            breakdown: Breakdown::default(),
+            combo: 0,
+            last_tag: None,
+            popups: Vec::new(),
+            tags: 0,
+            caught: false,
+            inputs: VecDeque::new(),
+            recorded: Vec::new(),
+            tuned: false,
+            events: Vec::new(),
+            switching: 0,
+        };
+        if climb {
+            world.spawn(settings.enemies);
+        } else {
+            world.next_wave();
+        }
+        world
+    }
+
+    /// Starts the next wave: a new map closing in a little more, and more
+    /// enemies than the last wave, of harder kinds.
+    fn next_wave(&mut self) {
+        self.wave += 1;
+        self.margin = ((self.wave as isize - 1) * 2).min(self.map.w / 4);
+        self.map = gen_map(self.map.w, self.map.h, self.margin, &mut self.rand);
+        let (left, right) = (self.margin + 1, self.map.w - 2 - self.margin);
+        let player = &mut self.player;
+        if !(left..=right).contains(&player.pos.x) {
+            player.place(Pos {
+                x: player.pos.x.clamp(left, right),
+                y: player.pos.y,
+            });
+        }
+        let count = (self.first_wave + self.wave - 1).min(MAX_WAVE_ENEMIES);
+        self.spawn(count);
+        self.switching = 60;
+    }
+
+    /// Replaces the enemies with `count` new ones, inside the walls.
+    fn spawn(&mut self, count: usize) {
+        let rand = &mut self.rand;
+        let map = &self.map;
+        let (left, right) = (self.margin + 1, map.w - 2 - self.margin);
+        self.enemies = (0..count)
+            .map(|i| {
+                let mut char = Char {
+                    kind: Kind::pick(self.mode, self.wave.max(1), i, rand),
+                    ..Char::default()
+                };
+                // On the screen they start from the sides of the floor, in
+                // larger levels they are scattered around and fall onto
+                // something.
+                let pos = if self.level == Level::Screen {
+                    let r = (rand.next() % 30) as isize;
+                    Pos {
+                        x: if rand.next() % 2 == 0 {
+                            left + r
+                        } else {
+                            right - r
+                        }
+                        .clamp(left, right),
+                        y: map.top + map.h - 2,
+                    }
+                } else {
+                    Pos {
+                        x: left + (rand.next() % (right - left + 1) as usize) as isize,
+                        y: map.top + 1 + (rand.next() % (map.h - 2) as usize) as isize,
+                    }
+                };
+                char.place(pos);
+                char
+            })
+            .collect();
+    }
+
+    pub fn press(&mut self, action: Action) {
+        press(&mut self.player, action, self.frames, &self.physics);
+        self.recorded.push((self.frames, action));
+        if self.enemies.iter().any(|e| e.kind == Kind::Mimic) {
+            self.inputs.push_back((self.frames, action));
+        }
+    }
+
+    pub fn step(&mut self) {
+        self.events.clear();
+        self.frames += 1;
+        let frames = self.frames;
+        let rand = &mut self.rand;
+        let player = &mut self.player;
+        update_char(player, &self.map, &self.physics, &mut self.events);
+        self.switching = (self.switching - 1).max(0);
+
+        let mut echo = Vec::new();
+        while let Some(&(frame, action)) = self.inputs.front() {
+            if frame + MIMIC_DELAY > frames {
+                break;
+            }
+            echo.push(action);
+            self.inputs.pop_front();
+        }
+        let senses = Senses {
+            player,
+            map: &self.map,
+            physics: &self.physics,
+            reaction: self.difficulty.reaction() * (3 + self.wave.min(9) as isize) / 4,
+            frames,
+            echo: &echo,
+        };
+        for ennemy in self.enemies.iter_mut() {
+            think(ennemy, &senses, rand, &mut self.events);
+            update_char(ennemy, &self.map, &self.physics, &mut self.events);
+        }
+
+        // Collisions are checked once everyone has moved, so that it does
+        // not matter who moved first.
+        let mut i = 0;
+        while i < self.enemies.len() {
+            let ennemy = &mut self.enemies[i];
+            i += 1;
+            if !player.crossed(ennemy) {
+                continue;
+            }
+            // A hunter catches the player unless it was stomped, dashed
+            // into or is still stunned.
+            let attacked = player.stomps(ennemy) || matches!(player.dash, Dash::Dashing(_));
+            let caught = ennemy.kind == Kind::Hunter && !attacked && ennemy.stun == 0;
+            let live = self.switching == 0 && self.mode != Mode::Climb;
+            if !live || caught {
+                collide(player, ennemy, &self.physics, &mut self.events);
+                if live {
+                    self.caught = self.mode == Mode::Survival;
+                }
+                continue;
+            }
+            // Before the stomp, which gives the player its double jump back.
+            let tag = Tag {
+                kind: ennemy.kind,
+                pos: ennemy.pos,
+                air: player.fly,
+                double_jump: player.fly && !player.double_jump_ready,
+                dash: matches!(player.dash, Dash::Dashing(_)),
+            };
+            // Tagged enemies are out of the wave, so they do not get to stun
+            // or push the player on the way out. Stomping one still bounces.
+            if player.stomps(ennemy) {
+                stomp(player, ennemy, &self.physics, &mut self.events);
+            }
+            self.events.push(GameEvent::Tag(tag));
+            i -= 1;
+            self.enemies.remove(i);
+        }
+        if self.enemies.is_empty() && self.mode != Mode::Climb {
+            self.events.push(GameEvent::WaveClear);
+            self.next_wave();
+        }
+        for i in 1..self.enemies.len() {
+            let (others, rest) = self.enemies.split_at_mut(i);
+            let ennemy = &mut rest[0];
+            for other in others.iter_mut() {
+                if ennemy.crossed(other) {
+                    bounce(ennemy, other, &self.physics);
+                }
+            }
+        }
+
+        if self.mode == Mode::Climb {
+            self.climb();
+        } else {
+            self.camera.follow(&self.player);
+        }
+        for i in 0..self.events.len() {
+            let event = self.events[i];
+            self.award(event);
+            self.effects(event);
+        }
+        self.trails();
+        update_particles(&mut self.particles);
+        update_afterimages(&mut self.afterimages);
+        update_popups(&mut self.popups);
+    }
+
+    /// Scores tags, with their bonuses, and shows what they were worth.
+    fn award(&mut self, event: GameEvent) {
+        let GameEvent::Tag(tag) = event else {
+            return;
+        };
+        let mut points = tag.kind.points();
+        self.breakdown.enemies += points;
+        let mut labels = Vec::new();
+        for (earned, bonus, total, label) in [
+            (tag.air, AIR_BONUS, &mut self.breakdown.air, "AIR"),
+            (
+                tag.double_jump,
+                DOUBLE_JUMP_BONUS,
+                &mut self.breakdown.double_jump,
+                "DOUBLE",
+            ),
+            (tag.dash, DASH_BONUS, &mut self.breakdown.dash, "DASH"),
+        ] {
+            if earned {
+                points += bonus;
+                *total += bonus;
+                labels.push(label.to_string());
+            }
+        }
+        let chained = self
+            .last_tag
+            .is_some_and(|t| self.frames - t <= COMBO_WINDOW);
+        self.combo = if chained { self.combo + 1 } else { 1 };
+        self.last_tag = Some(self.frames);
+        let multiplier = self.combo.min(MAX_MULTIPLIER);
+        if multiplier > 1 {
+            let extra = points * (multiplier - 1);
+            points += extra;
+            self.breakdown.combo += extra;
+            labels.push(format!("COMBO x{}", multiplier));
+        }
+        self.score += points;
+        self.tags += 1;
+        let text = format!("+{} {}", points, labels.join(" "));
+        spawn_popup(
+            &mut self.popups,
+            tag.pos,
+            text.trim_end().to_string(),
+            style::Color::Yellow,
+        );
+    }
+
+    /// Particles for what happened.
+    fn effects(&mut self, event: GameEvent) {
+        let (particles, fx) = (&mut self.particles, &mut self.fx);
+        match event {
+            GameEvent::TakeOff { kind, pos } | GameEvent::Land { kind, pos } => {
+                emit(particles, Effect::Dust, pos, fx, kind.color())
+            }
+            GameEvent::Hit { pos } => emit(particles, Effect::Sparks, pos, fx, style::Color::White),
+            GameEvent::Teleport { from, to } => {
+                emit(particles, Effect::Teleport, from, fx, style::Color::Magenta);
+                emit(particles, Effect::Teleport, to, fx, style::Color::Magenta);
+            }
+            GameEvent::Tag(tag) => {
+                emit(particles, Effect::Explosion, tag.pos, fx, tag.kind.color());
+                emit(particles, Effect::Sparks, tag.pos, fx, style::Color::White);
+            }
+            _ => {}
+        }
+    }
+
+    /// Leaves a trail and afterimages behind everyone dashing.
+    fn trails(&mut self) {
+        for char in std::iter::once(&self.player).chain(&self.enemies) {
+            leave_afterimages(&mut self.afterimages, char, self.frames);
+            if let Dash::Dashing(_) = char.dash {
+                emit(
+                    &mut self.particles,
+                    Effect::Trail,
+                    char.old_pos,
+                    &mut self.fx,
+                    char.kind.color(),
+                );
+            }
+        }
+    }
+
+    pub fn breakdown(&self) -> &Breakdown {
+        &self.breakdown
+    }
+
+    /// Scrolls up, faster and faster, growing the level above the screen
+    /// and forgetting what fell below it. Enemies left behind come back
+    /// from the top, and the score is the height reached.
+    fn climb(&mut self) {
+        let climbing = self.frames - CLIMB_DELAY * FPS as isize;
+        let speed = if climbing > 0 {
+            (CLIMB_SPEED + climbing / (CLIMB_RAMP * FPS as isize) * CLIMB_SPEED).min(CLIMB_MAX)
+        } else {
+            0
+        };
+        self.camera.climb(speed, &self.player);
+        let origin = self.camera.origin();
+        while self.map.top >= origin.y {
+            self.map.grow(&mut self.rand);
+        }
+        self.map.cut(origin.y + H + 2);
+        for ennemy in self.enemies.iter_mut() {
+            if ennemy.pos.y >= origin.y + H {
+                let x = 1 + (self.rand.next() % (self.map.w - 2) as usize) as isize;
+                ennemy.place(Pos { x, y: origin.y + 1 });
+            }
+        }
+        let height = (H - 2 - self.player.pos.y).max(0) as usize;
+        self.score = self.score.max(height);
+    }
+
+    /// Swaps the physics of a running world, for tuning.
+    pub fn set_physics(&mut self, physics: &PhysicsProfile) {
+        self.tuned |= self.frames > 0 && *physics != self.physics;
+        self.physics = physics.clone();
+    }
+
+    pub fn frames(&self) -> isize {
+        self.frames
+    }
+
+    pub fn tags(&self) -> usize {
+        self.tags
+    }
+
+    /// Tags chained in the current or last combo.
+    pub fn combo(&self) -> usize {
+        self.combo
+    }
+
+    pub fn wave(&self) -> usize {
+        self.wave
+    }
+
+    pub fn events(&self) -> &[GameEvent] {
+        &self.events
+    }
+
+    /// What it takes to play this run again, unless the physics were tuned
+    /// along the way.
+    pub fn replay(&self) -> Option<Replay> {
+        (!self.tuned).then(|| Replay {
+            version: VERSION,
+            seed: self.seed,
+            mode: self.mode,
+            level: self.level,
+            difficulty: self.difficulty,
+            enemies: self.first_wave,
+            physics: self.physics.clone(),
+            inputs: self.recorded.clone(),
+            score: self.score,
+            frames: self.frames,
+        })
+    }
+
+    pub fn score(&self) -> usize {
+        self.score
+    }
+
+    /// Whether the world was set up with these settings, or needs to be
+    /// rebuilt before playing with them.
+    pub fn matches(&self, settings: &Settings) -> bool {
+        // The daily challenge has its own settings, and a level of its own
+        // each day.
+        if settings.mode == Mode::Daily {
+            return self.mode == Mode::Daily && self.seed == Date::today().seed();
+        }
+        self.mode == settings.mode
+            && self.level == settings.level
+            && self.difficulty == settings.difficulty
+            && self.first_wave == settings.enemies
+    }
+
+    /// Whether the run is over under the rules of its mode.
+    pub fn finished(&self) -> bool {
+        match self.mode {
+            Mode::Classic => false,
+            Mode::TimeAttack => self.tags >= Mode::TIME_ATTACK_TAGS,
+            Mode::Blitz | Mode::Daily => self.frames >= Mode::BLITZ_SECONDS * FPS as isize,
+            Mode::Climb => self.player.pos.y >= self.camera.origin().y + H,
+            Mode::Survival => self.caught,
+        }
+    }
+
+    /// What the high scores keep for this run.
+    pub fn result(&self) -> u64 {
+        if self.mode.lower_is_better() {
+            self.frames as u64
+        } else {
+            self.score as u64
+        }
+    }
+
+    pub fn draw(&self, screen: &mut Screen, palette: &Palette, sprites: &Sprites) {
+        let origin = self.camera.origin();
+        let pixels = &mut screen.pixels;
+        for y in 0..H {
+            for x in 0..W {
+                let index = (x + y * W) as usize;
+                let cell = self.map.get(origin.x + x, origin.y + y);
+                let mut color = match cell {
+                    Cell::Wall => palette.wall,
+                    Cell::Air => style::Color::Black,
+                    Cell::Solid => palette.solid,
+                };
+                if self.switching > 0 {
+                    color = palette.wall;
+                }
+                pixels[index] = Pixel {
+                    back: color,
+                    front: color,
+                    char: ' ',
+                };
+            }
+        }
+        if self.switching > 0 {
+            let banner = format!("WAVE {}", self.wave);
+            screen.banner(H / 2 - 5, &banner, style::Color::Green);
+        }
+
+        draw_afterimages(&self.afterimages, sprites, screen, origin);
+        for c in self.enemies.iter().chain(std::iter::once(&self.player)) {
+            let falling = c.fly && c.vy >= self.physics.max_fall * 3 / 4;
+            draw_speed_lines(c, falling, screen, origin);
+        }
+        for c in self.enemies.iter().chain(std::iter::once(&self.player)) {
+            let (logo, half) = sprites.frame(c, self.frames);
+            for p in c.trail().take_while(|p| *p != c.pos) {
+                let pixel = Pixel {
+                    back: c.kind.trail_color(),
+                    front: style::Color::Black,
+                    char: logo,
+                };
+                screen.put(p.x - origin.x, p.y - origin.y, pixel);
+            }
+            {
+                let pixel = Pixel {
+                    back: match (c.kind, c.dash) {
+                        (Kind::Player, Dash::Dashing(_)) => style::Color::White,
+                        (Kind::Player, Dash::Loading(_)) => style::Color::DarkYellow,
+                        (kind, _) => kind.color(),
+                    },
+                    front: style::Color::Black,
+                    char: logo,
+                };
+                let (x, y) = (c.pos.x - origin.x, c.pos.y - origin.y);
+                screen.put(x, y, pixel);
+                if let Some((char, side)) = half {
+                    screen.put_blank(x + side, y, Pixel { char, ..pixel });
+                }
+            }
+            draw_pose(c, screen, origin);
+        }
+
+        let pixels = &mut screen.pixels;
+        {
+            for (x, c) in (1..).zip("yjump ".chars().chain(env!("CARGO_PKG_VERSION").chars())) {
+                pixels[x] = Pixel {
+                    back: palette.hud,
+                    front: style::Color::White,
+                    char: c,
+                };
+            }
+        }
+        {
+            let alt = self.switching > 0 && self.switching % 8 < 4;
+            let sep = if alt { '-' } else { ' ' };
+
+            let s = if self.wave > 0 {
+                format!("{}Score: {}  Wave: {}{}", sep, self.score, self.wave, sep)
+            } else {
+                format!("{}Score: {}{}", sep, self.score, sep)
+            };
+            for (x, c) in (W as usize / 2 - s.len() / 2..).zip(s.chars()) {
+                pixels[x] = Pixel {
+                    back: palette.hud,
+                    front: style::Color::White,
+                    char: c,
+                };
+            }
+        }
+        {
+            let s = match self.mode {
+                Mode::Classic => String::new(),
+                Mode::TimeAttack => format!(
+                    "{}/{} {} ",
+                    self.tags,
+                    Mode::TIME_ATTACK_TAGS,
+                    format_time(self.frames)
+                ),
+                Mode::Blitz | Mode::Daily => format!(
+                    "{} left ",
+                    format_time(Mode::BLITZ_SECONDS * FPS as isize - self.frames)
+                ),
+                Mode::Climb | Mode::Survival => format!("{} ", format_time(self.frames)),
+            };
+            for (x, c) in (W as usize - 1 - s.len()..).zip(s.chars()) {
+                pixels[x] = Pixel {
+                    back: palette.hud,
+                    front: style::Color::White,
+                    char: c,
+                };
+            }
+        }
+
+        if self.mode != Mode::Climb && self.level != Level::Screen {
+            self.draw_minimap(screen, palette);
+        }
+        draw_particles(&self.particles, screen, origin);
+        draw_popups(&self.popups, screen, origin);
+    }
+
+    /// Everything there is to know to carry on with the run, as the lines
+    /// of a save file.
+    pub fn to_save(&self) -> String {
+        let b = &self.breakdown;
+        let mut text = format!(
+            "seed = {}\nmode = {}\nlevel = {}\ndifficulty = {}\nenemies = {}\n\
+             rand = {}\nfx = {}\nwave = {}\nmargin = {}\nframes = {}\nscore = {}\n\
+             breakdown = {} {} {} {} {}\ncombo = {}\nlast_tag = {}\ntags = {}\n\
+             caught = {}\ntuned = {}\nofficial = {}\nswitching = {}\ncamera = {}\nmap = {} {} {}\n",
+            self.seed,
+            self.mode.key(),
+            self.level.key(),
+            self.difficulty.key(),
+            self.first_wave,
+            self.rand.0,
+            self.fx.0,
+            self.wave,
+            self.margin,
+            self.frames,
+            self.score,
+            b.enemies,
+            b.air,
+            b.double_jump,
+            b.dash,
+            b.combo,
+            self.combo,
+            self.last_tag.map_or("none".into(), |t| t.to_string()),
+            self.tags,
+            self.caught,
+            self.tuned,
+            self.official,
+            self.switching,
+            self.camera.to_save(),
+            self.map.w,
+            self.map.h,
+            self.map.top,
+        );
+        let mut line = |key: &str, value: String| text += &format!("{} = {}\n", key, value);
+        for (key, value) in self.physics.values() {
+            line(&format!("physics.{}", key), value.to_string());
+        }
+        for row in 0..self.map.h {
+            line("row", self.map.row_to_save(row));
+        }
+        line("player", self.player.to_save());
+        for ennemy in &self.enemies {
+            line("enemy", ennemy.to_save());
+        }
+        for particle in &self.particles {
+            line("particle", particle.to_save());
+        }
+        for popup in &self.popups {
+            line("popup", popup.to_save());
+        }
+        for (tick, action) in &self.inputs {
+            line("echo", format!("{} {}", tick, action.key()));
+        }
+        for (tick, action) in &self.recorded {
+            line("input", format!("{} {}", tick, action.key()));
+        }
+        text
+    }
+
+    /// Reads back what `to_save` wrote.
+    pub fn from_save(name: &str, text: &str) -> Result<Self, Error> {
+        let mut world = World::new(
+            1,
+            &Settings::default(),
+            &PhysicsProfile::preset(Preset::Classic),
+        );
+        world.enemies.clear();
+        let mut rows = 0;
+        let input = |value: &str| {
+            let mut f = Fields::new(value);
+            let tick = f.next()?;
+            let action = f.word()?;
+            Action::parse(action)
+                .map(|a| (tick, a))
+                .ok_or_else(|| format!("unknown action `{}`", action))
+        };
+        store::parse(name, text, |key, value| {
+            let mut f = Fields::new(value);
+            match key {
+                "version" => {}
+                "seed" => world.seed = f.next()?,
+                "mode" => world.mode = Mode::parse(value)?,
+                "level" => world.level = Level::parse(value)?,
+                "difficulty" => world.difficulty = Difficulty::parse(value)?,
+                "enemies" => world.first_wave = f.next()?,
+                "rand" => world.rand = Rand(f.next()?),
+                "fx" => world.fx = Rand(f.next()?),
+                "wave" => world.wave = f.next()?,
+                "margin" => world.margin = f.next()?,
+                "frames" => world.frames = f.next()?,
+                "score" => world.score = f.next()?,
+                "breakdown" => {
+                    world.breakdown = Breakdown {
+                        enemies: f.next()?,
+                        air: f.next()?,
+                        double_jump: f.next()?,
+                        dash: f.next()?,
+                        combo: f.next()?,
+                    }
+                }
+                "combo" => world.combo = f.next()?,
+                "last_tag" => world.last_tag = value.parse().ok(),
+                "tags" => world.tags = f.next()?,
+                "caught" => world.caught = f.next()?,
+                "tuned" => world.tuned = f.next()?,
+                "official" => world.official = f.next()?,
+                "switching" => world.switching = f.next()?,
+                "camera" => world.camera = Camera::from_save(f)?,
+                "map" => {
+                    world.map = Map {
+                        w: f.next()?,
+                        h: f.next()?,
+                        top: f.next()?,
+                        cells: Vec::new(),
+                    }
+                }
+                "row" => {
+                    world.map.row_from_save(value)?;
+                    rows += 1;
+                }
+                "player" => world.player = Char::from_save(f)?,
+                "enemy" => world.enemies.push(Char::from_save(f)?),
+                "particle" => world.particles.push(Particle::from_save(f)?),
+                "popup" => world.popups.push(Popup::from_save(f)?),
+                "echo" => world.inputs.push_back(input(value)?),
+                "input" => world.recorded.push(input(value)?),
+                _ => match key.strip_prefix("physics.") {
+                    Some(key) => world.physics.set(key, value)?,
+                    None => return Err(format!("unknown save value `{}`", key)),
+                },
+            }
+            Ok(())
+        })?;
+        if rows != world.map.h {
+            return Err(Error::Config(format!(
+                "{}: the map has {} rows, not {}",
+                name, rows, world.map.h
+            )));
+        }
+        Ok(world)
+    }
+
+    /// Draws the player of another run over this one, as a dim sprite that
+    /// lets whatever is under it show through.
+    pub fn draw_ghost(&self, screen: &mut Screen, ghost: &World, sprites: &Sprites) {
+        let origin = self.camera.origin();
+        let c = &ghost.player;
+        let (x, y) = (c.pos.x - origin.x, c.pos.y - origin.y);
+        let (logo, half) = sprites.frame(c, ghost.frames);
+        for (x, char) in std::iter::once((x, logo)).chain(half.map(|(h, side)| (x + side, h))) {
+            if (0..W).contains(&x) && (0..H).contains(&y) {
+                let pixel = &mut screen.pixels[(x + y * W) as usize];
+                pixel.char = char;
+                pixel.front = style::Color::DarkGrey;
+            }
+        }
+    }
+
+    /// The whole level scaled down in the top right corner, with the part
+    /// on screen as a window into it.
+    fn draw_minimap(&self, screen: &mut Screen, palette: &Palette) {
+        let ceil = |a: isize, b: isize| (a + b - 1) / b;
+        let scale = ceil(self.map.w, MINIMAP_W).max(ceil(self.map.h, MINIMAP_H));
+        let (w, h) = (ceil(self.map.w, scale), ceil(self.map.h, scale));
+        let (x0, y0) = (W - 1 - w, 1);
+        let origin = self.camera.origin();
+        for my in 0..h {
+            for mx in 0..w {
+                let (x, y) = (mx * scale + scale / 2, my * scale + scale / 2);
+                let shown =
+                    (origin.x..origin.x + W).contains(&x) && (origin.y..origin.y + H).contains(&y);
+                let back = if shown {
+                    style::Color::Black
+                } else {
+                    palette.hud
+                };
+                screen.put(
+                    x0 + mx,
+                    y0 + my,
+                    Pixel {
+                        back,
+                        front: back,
+                        char: ' ',
+                    },
+                );
+            }
+        }
+        for c in self.enemies.iter().chain(std::iter::once(&self.player)) {
+            let color = c.kind.color();
+            screen.put(
+                x0 + c.pos.x / scale,
+                y0 + c.pos.y / scale,
+                Pixel {
+                    back: color,
+                    front: color,
+                    char: ' ',
+                },
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::{collide, move_x, update_char, Action, Char, Dash, Kind, Map, Pos, World, SUB};
+    use crate::{
+        physics::{PhysicsProfile, Preset},
+        settings::Settings,
+    };
+
+    /// Steps a run one tick along a script that runs about, jumps, dashes
+    /// and stops every so often, for tests that need a run.
+    pub fn play(world: &mut World) {
+        let action = match world.frames() % 90 {
+            0 | 4 => Some(Action::Right),
+            20 | 40 => Some(Action::Jump),
+            45 | 48 => Some(Action::Left),
+            70 => Some(Action::Down),
+            _ => None,
+        };
+        if let Some(action) = action {
+            world.press(action);
+        }
+        world.step();
+    }
+
+    /// A level from rows of save file cells, with walls all around.
+    fn level(rows: &[&str]) -> Map {
+        let mut map = Map {
+            w: rows[0].len() as isize,
+            h: rows.len() as isize,
+            top: 0,
+            cells: Vec::new(),
+        };
+        for row in rows {
+            map.row_from_save(row).unwrap();
+        }
+        map
+    }
+
+    fn char_at(x: isize, y: isize) -> Char {
+        let mut char = Char::default();
+        char.place(Pos { x, y });
+        char
+    }
+
+    /// Steps a character until it lands, returning how many cells it rose
+    /// and how far it went sideways.
+    fn jump(right_power: isize, physics: &PhysicsProfile) -> (isize, isize) {
+        let map = level(&["................................"; 16]);
+        let mut char = char_at(16, 15);
+        char.right_power = right_power;
+        char.jump = 1;
+        let mut top = char.pos.y;
+        update_char(&mut char, &map, physics, &mut Vec::new());
+        while char.fly {
+            update_char(&mut char, &map, physics, &mut Vec::new());
+            top = top.min(char.pos.y);
+        }
+        assert_eq!(char.pos.y, 15);
+        (15 - top, char.pos.x - 16)
+    }
+
+    #[test]
+    fn jumps_are_as_high_as_before_sub_cells() {
+        let physics = PhysicsProfile::preset(Preset::Classic);
+        assert_eq!(jump(0, &physics), (10, 0));
+        assert_eq!(jump(1, &physics), (7, 11));
+        assert_eq!(jump(-1, &physics), (7, -11));
+    }
+
+    #[test]
+    fn fast_falls_land_on_thin_platforms() {
+        let physics = PhysicsProfile {
+            max_fall: 3 * SUB,
+            ..PhysicsProfile::preset(Preset::Classic)
+        };
+        let mut rows = vec!["........"; 30];
+        rows[20] = "..====..";
+        let map = level(&rows);
+        let mut char = char_at(3, 0);
+        char.fly = true;
+        char.vy = physics.max_fall;
+        for _ in 0..60 {
+            update_char(&mut char, &map, &physics, &mut Vec::new());
+        }
+        assert!(!char.fly);
+        assert_eq!((char.pos.x, char.pos.y), (3, 19));
+    }
+
+    #[test]
+    fn dashes_end_at_running_speed_in_the_air() {
+        let physics = PhysicsProfile::preset(Preset::Classic);
+        let map = level(&["........"; 8]);
+        let mut char = char_at(2, 3);
+        char.fly = true;
+        char.right_power = 1;
+        char.dash = Dash::Dashing(0);
+        update_char(&mut char, &map, &physics, &mut Vec::new());
+        assert!(char.dash == Dash::Loading(physics.dash_cooldown));
+        assert_eq!(char.vx, physics.run);
+
+        // On the ground it stops.
+        let mut char = char_at(2, 7);
+        char.right_power = 1;
+        char.dash = Dash::Dashing(0);
+        update_char(&mut char, &map, &physics, &mut Vec::new());
+        assert_eq!(char.vx, 0);
+        assert_eq!(char.pos.x, 2);
+    }
+
+    #[test]
+    fn platforms_block_from_the_side_but_not_from_inside() {
+        let map = level(&["....===.#..."]);
+        let mut char = char_at(1, 0);
+        char.vx = 4 * SUB;
+        move_x(&mut char, &map);
+        assert_eq!((char.pos.x, char.vx), (3, 0));
+
+        // Already inside on the way up, it moves along the platform and out.
+        let mut char = char_at(5, 0);
+        char.vx = 2 * SUB;
+        move_x(&mut char, &map);
+        assert_eq!((char.pos.x, char.vx), (7, 2 * SUB));
+
+        // Walls block from anywhere.
+        char.vx = 2 * SUB;
+        move_x(&mut char, &map);
+        assert_eq!((char.pos.x, char.vx), (7, 0));
+    }
+
+    #[test]
+    fn pressing_into_a_wall_slows_the_fall() {
+        let physics = PhysicsProfile::preset(Preset::Classic);
+        let map = level(&["#......."; 40]);
+        for (right_power, capped) in [(-1, true), (1, false)] {
+            let mut char = char_at(1, 0);
+            char.fly = true;
+            char.right_power = right_power;
+            for _ in 0..30 {
+                update_char(&mut char, &map, &physics, &mut Vec::new());
+            }
+            assert_eq!(char.pos.x, 1);
+            assert_eq!(char.vy <= physics.wall_slide, capped);
+        }
+    }
+
+    #[test]
+    fn wall_jumps_give_the_double_jump_back() {
+        let physics = PhysicsProfile::preset(Preset::Classic);
+        let map = level(&["#......."; 16]);
+        let mut char = char_at(1, 8);
+        char.fly = true;
+        char.right_power = -1;
+        char.double_jump_ready = false;
+        char.jump = 1;
+        update_char(&mut char, &map, &physics, &mut Vec::new());
+        assert!(char.double_jump_ready);
+        assert_eq!(char.right_power, 1);
+        assert_eq!(char.vx, physics.run);
+        assert_eq!(char.vy, -physics.wall_jump + physics.gravity);
+
+        // And it can be used once away from the wall.
+        while char.pos.x == 1 {
+            update_char(&mut char, &map, &physics, &mut Vec::new());
+        }
+        char.jump = 1;
+        update_char(&mut char, &map, &physics, &mut Vec::new());
+        assert!(!char.double_jump_ready);
+        assert_eq!(char.vy, char.jump_speed(&physics) + physics.gravity);
+    }
+
+    #[test]
+    fn stomps_stun_and_stop_dashes() {
+        let physics = PhysicsProfile::preset(Preset::Classic);
+        let mut top = char_at(5, 3);
+        top.double_jump_ready = false;
+        top.pos.y = 4;
+        let mut bottom = char_at(5, 4);
+        bottom.dash = Dash::Dashing(5);
+        let mut events = Vec::new();
+        collide(&mut bottom, &mut top, &physics, &mut events);
+        assert_eq!(bottom.stun, physics.stun);
+        assert!(bottom.dash == Dash::Loading(physics.dash_cooldown));
+        assert_eq!(top.vy, -physics.jump);
+        assert!(top.double_jump_ready);
+        assert_eq!(top.stun, 0);
+        assert_eq!(events.len(), 1);
+    }
+
+    #[test]
+    fn dashes_knock_back() {
+        let physics = PhysicsProfile::preset(Preset::Classic);
+        let mut dasher = char_at(4, 5);
+        dasher.right_power = -1;
+        dasher.dash = Dash::Dashing(5);
+        let mut other = char_at(4, 5);
+        let mut events = Vec::new();
+        collide(&mut other, &mut dasher, &physics, &mut events);
+        assert!(other.fly);
+        assert_eq!(
+            (other.vx, other.vy),
+            (-physics.knockback, -physics.knockback)
+        );
+        assert_eq!((dasher.vx, dasher.vy), (0, 0));
+        assert_eq!(events.len(), 1);
+    }
+
+    #[test]
+    fn bumps_push_apart_the_way_they_came() {
+        let physics = PhysicsProfile::preset(Preset::Classic);
+        let mut left = char_at(4, 5);
+        left.pos.x = 5;
+        let mut right = char_at(5, 5);
+        let mut events = Vec::new();
+        collide(&mut right, &mut left, &physics, &mut events);
+        assert_eq!((left.vx, left.vy), (-physics.bounce, -physics.bounce));
+        assert_eq!((right.vx, right.vy), (physics.bounce, -physics.bounce));
+        assert!(left.fly && right.fly);
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn tagged_enemies_do_not_stun_on_the_way_out() {
+        let mut world = World::new(
+            3,
+            &Settings::default(),
+            &PhysicsProfile::preset(Preset::Classic),
+        );
+        world.map = level(&["................"; 8]);
+        world.player = char_at(4, 7);
+        let mut falling = char_at(4, 6);
+        falling.kind = Kind::Heavy;
+        falling.fly = true;
+        falling.vy = SUB;
+        let mut away = char_at(12, 7);
+        away.kind = Kind::Heavy;
+        world.enemies = vec![falling, away];
+        world.switching = 0;
+        world.step();
+        assert_eq!(world.tags(), 1);
+        assert_eq!(world.player.stun, 0);
+    }
+}